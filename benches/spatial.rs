@@ -0,0 +1,89 @@
+// `World::tick` with 10k enemies, to check the spatial hash keeps the
+// simulation above 60 ticks per second. The player purges every so often so
+// the destroy path is timed too.
+//
+//     cargo bench --bench spatial
+
+use std::time::{Duration, Instant};
+
+use upsi3::{
+    archetypes::Archetypes,
+    director::WaveScript,
+    entities::{random_outside_pos, Entity},
+    events::Event,
+    input::Input,
+    world::World,
+};
+
+const ENEMIES: usize = 10_000;
+const TICKS: u32 = 600;
+const TARGET_TPS: f64 = 60.;
+const SEED: u64 = 1234;
+/// Ticks between two purges
+const POWER_EVERY: u32 = 30;
+
+/// Tops the arena back up to `ENEMIES`, cycling through the archetypes like
+/// the console's `spawn` does.
+fn spawn(world: &mut World) {
+    let mut archetype = world.enemies.len();
+    while world.enemies.len() < ENEMIES {
+        let stats = world.archetypes.stats(archetype % world.archetypes.len());
+        let pos = random_outside_pos(&mut world.rng);
+        let enemy = Entity::spawn(stats, pos, world.player.pos, &mut world.rng);
+        world.enemies.push(enemy);
+        archetype += 1;
+    }
+}
+
+fn main() {
+    let archetypes = Archetypes::load();
+    let script = WaveScript::load(&archetypes);
+    let mut world = World::with_seed(SEED, archetypes, script);
+    world.has_game_started = true;
+    // A BSOD would empty the arena, so nothing can crash the run
+    world.godmode = true;
+    for achievement in &mut world.achievements.achievements {
+        achievement.unlocked = true;
+    }
+
+    let idle = Input::default();
+    let purge = Input {
+        power: true,
+        ..Input::default()
+    };
+    let mut events = Vec::new();
+    let mut elapsed = Duration::ZERO;
+    let mut destroyed = 0;
+    for tick in 0..TICKS {
+        spawn(&mut world);
+        // The known bugs the purges run into would add up to a BSOD
+        world.instability = 0;
+        events.clear();
+        let input = if tick % POWER_EVERY == POWER_EVERY - 1 {
+            world.mana = 3;
+            &purge
+        } else {
+            &idle
+        };
+        let start = Instant::now();
+        world.tick(input, &mut events);
+        elapsed += start.elapsed();
+
+        assert!(
+            !events.iter().any(|e| matches!(e, Event::Bsod(_))),
+            "the run crashed"
+        );
+        destroyed += events
+            .iter()
+            .filter(|e| matches!(e, Event::EnemyDestroyed { .. }))
+            .count();
+    }
+    let tps = f64::from(TICKS) / elapsed.as_secs_f64();
+
+    println!("World::tick {tps:>10.0} ticks/s ({destroyed} enemies destroyed)");
+    assert!(destroyed > 0, "the purges destroyed nothing");
+    assert!(
+        tps >= TARGET_TPS,
+        "below {TARGET_TPS} ticks/s with {ENEMIES} enemies"
+    );
+}
@@ -0,0 +1,291 @@
+use crate::{
+    entities::EntityType,
+    events::{Event, Sfx},
+    world::World,
+};
+
+use macroquad::math::Vec2;
+
+const DASH_IMPULSE: f32 = 0.8;
+const DASH_TICKS: u32 = 15;
+pub const SHIELD_TICKS: u32 = 180;
+const SLOW_TICKS: u32 = 240;
+const REFLECTOR_TICKS: u32 = 90;
+/// Enemies closer than this to the player only move every other tick while
+/// the time-slow field is up
+pub const SLOW_RANGE: f32 = 6.;
+/// Bullets closer than this to the player bounce back while the reflector
+/// is up
+pub const REFLECT_RANGE: f32 = 2.;
+
+/// Ticks left of each lasting effect of the abilities and pickups, read by
+/// the simulation and by the frontend.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct Effects {
+    /// Invulnerable, enemies go through the player
+    pub dash: u32,
+    /// Enemies touching the player are destroyed without hurting
+    pub shield: u32,
+    /// Time-slow field around the player
+    pub slow: u32,
+    pub reflector: u32,
+    /// Score multiplier pickup
+    pub score: u32,
+    /// Speed boost pickup
+    pub speed: u32,
+    pub magnet: u32,
+}
+
+impl Effects {
+    pub const fn tick(&mut self) {
+        self.dash = self.dash.saturating_sub(1);
+        self.shield = self.shield.saturating_sub(1);
+        self.slow = self.slow.saturating_sub(1);
+        self.reflector = self.reflector.saturating_sub(1);
+        self.score = self.score.saturating_sub(1);
+        self.speed = self.speed.saturating_sub(1);
+        self.magnet = self.magnet.saturating_sub(1);
+    }
+
+    /// Hits don't hurt the player.
+    pub const fn protected(&self) -> bool {
+        self.dash > 0 || self.shield > 0
+    }
+}
+
+/// Something the player casts with the power key.
+pub trait Ability {
+    fn name(&self) -> &'static str;
+
+    /// Mana spent per cast. Casting without enough still works, the mana
+    /// wraps around and the `unlimited_power` bug shows up.
+    fn cost(&self) -> u8;
+
+    /// Ticks before it can be cast again.
+    fn cooldown(&self) -> u32;
+
+    fn sfx(&self) -> Sfx;
+
+    fn cast(&mut self, world: &mut World, events: &mut Vec<Event>);
+}
+
+#[derive(Default)]
+pub struct Loadout {
+    abilities: Vec<Box<dyn Ability>>,
+    /// Ticks left before each ability can be cast again
+    cooldowns: Vec<u32>,
+    /// Index of the ability the power key casts
+    pub selected: usize,
+}
+
+impl Loadout {
+    /// All the built-in abilities, in number key order.
+    pub fn new() -> Self {
+        let mut loadout = Self::default();
+        loadout.register(Box::new(Purge));
+        loadout.register(Box::new(Dash));
+        loadout.register(Box::new(Shield));
+        loadout.register(Box::new(TimeSlow));
+        loadout.register(Box::new(Reflector));
+        loadout
+    }
+
+    pub fn register(&mut self, ability: Box<dyn Ability>) {
+        self.abilities.push(ability);
+        self.cooldowns.push(0);
+    }
+
+    /// Ignored if there is no ability in that slot.
+    pub fn select(&mut self, slot: usize) {
+        if slot < self.abilities.len() {
+            self.selected = slot;
+        }
+    }
+
+    /// Name, cost, ticks of cooldown left and full cooldown of each ability,
+    /// for the HUD.
+    pub fn slots(&self) -> impl Iterator<Item = (&'static str, u8, u32, u32)> + '_ {
+        self.abilities
+            .iter()
+            .zip(&self.cooldowns)
+            .map(|(a, &left)| (a.name(), a.cost(), left, a.cooldown()))
+    }
+
+    pub fn tick(&mut self) {
+        for cooldown in &mut self.cooldowns {
+            *cooldown = cooldown.saturating_sub(1);
+        }
+    }
+
+    /// Casts the selected ability unless it's cooling down.
+    pub fn cast(&mut self, world: &mut World, events: &mut Vec<Event>) {
+        let Some(ability) = self.abilities.get_mut(self.selected) else {
+            return;
+        };
+        if self.cooldowns[self.selected] > 0 {
+            return;
+        }
+
+        events.push(Event::AbilityUsed {
+            name: ability.name(),
+            sfx: ability.sfx(),
+        });
+        world.spend_mana(ability.cost(), events);
+        ability.cast(world, events);
+        self.cooldowns[self.selected] = ability.cooldown();
+    }
+
+    pub fn reset(&mut self) {
+        self.cooldowns.fill(0);
+        self.selected = 0;
+    }
+}
+
+/// The original power: destroys every enemy in `DESTROY_RANGE`.
+pub struct Purge;
+
+impl Ability for Purge {
+    fn name(&self) -> &'static str {
+        "purge"
+    }
+
+    fn cost(&self) -> u8 {
+        2
+    }
+
+    fn cooldown(&self) -> u32 {
+        0
+    }
+
+    fn sfx(&self) -> Sfx {
+        Sfx::Explosion
+    }
+
+    fn cast(&mut self, world: &mut World, events: &mut Vec<Event>) {
+        world.power_destroy(events);
+    }
+}
+
+/// A burst of speed in the current direction, invulnerable for a moment.
+pub struct Dash;
+
+impl Ability for Dash {
+    fn name(&self) -> &'static str {
+        "dash"
+    }
+
+    fn cost(&self) -> u8 {
+        1
+    }
+
+    fn cooldown(&self) -> u32 {
+        45
+    }
+
+    fn sfx(&self) -> Sfx {
+        Sfx::PickItem
+    }
+
+    fn cast(&mut self, world: &mut World, _events: &mut Vec<Event>) {
+        let player = &mut world.player;
+        let direction = player
+            .speed
+            .try_normalize()
+            .unwrap_or_else(|| Vec2::from_angle(player.rotation));
+        player.speed += direction * DASH_IMPULSE;
+        world.effects.dash = DASH_TICKS;
+    }
+}
+
+pub struct Shield;
+
+impl Ability for Shield {
+    fn name(&self) -> &'static str {
+        "shield"
+    }
+
+    fn cost(&self) -> u8 {
+        2
+    }
+
+    fn cooldown(&self) -> u32 {
+        300
+    }
+
+    fn sfx(&self) -> Sfx {
+        Sfx::PickItem
+    }
+
+    fn cast(&mut self, world: &mut World, _events: &mut Vec<Event>) {
+        world.effects.shield = SHIELD_TICKS;
+    }
+}
+
+/// Enemies in `SLOW_RANGE` move at half speed for a while.
+pub struct TimeSlow;
+
+impl Ability for TimeSlow {
+    fn name(&self) -> &'static str {
+        "time_slow"
+    }
+
+    fn cost(&self) -> u8 {
+        2
+    }
+
+    fn cooldown(&self) -> u32 {
+        600
+    }
+
+    fn sfx(&self) -> Sfx {
+        Sfx::Glitch
+    }
+
+    fn cast(&mut self, world: &mut World, _events: &mut Vec<Event>) {
+        world.effects.slow = SLOW_TICKS;
+    }
+}
+
+/// Bullets coming close bounce back and destroy the enemies they hit.
+pub struct Reflector;
+
+impl Ability for Reflector {
+    fn name(&self) -> &'static str {
+        "reflector"
+    }
+
+    fn cost(&self) -> u8 {
+        1
+    }
+
+    fn cooldown(&self) -> u32 {
+        120
+    }
+
+    fn sfx(&self) -> Sfx {
+        Sfx::PickItem
+    }
+
+    fn cast(&mut self, world: &mut World, _events: &mut Vec<Event>) {
+        world.effects.reflector = REFLECTOR_TICKS;
+    }
+}
+
+/// Turns bullets in `REFLECT_RANGE` of the player around, they now only hurt
+/// enemies.
+pub fn reflect_bullets(world: &mut World) {
+    let player = world.player.pos;
+    for bullet in &mut world.enemies {
+        if bullet.friendly
+            || !matches!(bullet.e_type, EntityType::Bullet)
+            || (bullet.pos - player).length() >= REFLECT_RANGE
+        {
+            continue;
+        }
+        let away = (bullet.pos - player)
+            .try_normalize()
+            .unwrap_or_else(|| -bullet.speed.normalize_or_zero());
+        bullet.speed = away * bullet.speed.length();
+        bullet.friendly = true;
+    }
+}
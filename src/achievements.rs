@@ -1,5 +1,6 @@
 use macroquad::prelude::*;
 use macroquad::{prelude::LIGHTGRAY, shapes::draw_rectangle};
+use nanoserde::DeRon;
 
 #[derive(Clone)]
 pub struct Achievements {
@@ -7,75 +8,78 @@ pub struct Achievements {
 }
 
 impl Achievements {
+    /// Definitions from `assets/achievements.ron`, all locked.
     pub fn new() -> Self {
-        Self {
-            achievements: vec![
-                Achievement::new(
-                    include_bytes!("../assets/images/name_overflow.png"),
-                    "name_overflow",
-                    "Crash the game by choosing a big name",
-                ),
-                Achievement::new(
-                    include_bytes!("../assets/images/unstable.png"),
-                    "unstable",
-                    "Crash the game by unstability",
-                ),
-                Achievement::new(
-                    include_bytes!("../assets/images/second_chance.png"),
-                    "second_chance",
-                    "Regain full health by underflowing HP",
-                ),
-                Achievement::new(
-                    include_bytes!("../assets/images/over_healed.png"),
-                    "over_healed",
-                    "Go back to zero health by regeneration",
-                ),
-                Achievement::new(
-                    include_bytes!("../assets/images/unlimited_power.png"),
-                    "unlimited_power",
-                    "Use the special ability without having any energy for it",
-                ),
-                Achievement::new(
-                    include_bytes!("../assets/images/over_9000.png"),
-                    "over_9000",
-                    "Overflow your energy back to zero by taking too much",
-                ),
-                Achievement::new(
-                    include_bytes!("../assets/images/up.png"),
-                    "up",
-                    "Leave the map",
-                ),
-            ],
+        Self::from_ron(include_str!("../assets/achievements.ron"))
+            .unwrap_or_else(|err| panic!("assets/achievements.ron: {err}"))
+    }
+
+    pub fn from_ron(ron: &str) -> Result<Self, String> {
+        let achievements = Vec::<Achievement>::deserialize_ron(ron).map_err(|e| e.to_string())?;
+
+        for (i, a) in achievements.iter().enumerate() {
+            if a.id.is_empty() {
+                return Err(format!("achievement #{i} has an empty id"));
+            }
+            if achievements[..i].iter().any(|other| other.id == a.id) {
+                return Err(format!("duplicate achievement id \"{}\"", a.id));
+            }
         }
+
+        Ok(Self { achievements })
     }
-}
 
-#[derive(Clone)]
-pub struct Achievement {
-    pub texture: Texture2D,
-    pub name: &'static str,
-    pub desc: &'static str,
-    pub unlocked: bool,
-}
+    pub fn get(&self, id: &str) -> Option<&Achievement> {
+        self.achievements.iter().find(|a| a.id == id)
+    }
 
-impl Achievement {
-    pub fn new(bytes: &[u8], name: &'static str, desc: &'static str) -> Self {
-        let texture = Texture2D::from_file_with_format(bytes, None);
-        texture.set_filter(FilterMode::Nearest);
-        Self {
-            texture,
-            name,
-            desc,
-            unlocked: false,
+    pub fn is_unlocked(&self, id: &str) -> bool {
+        self.get(id).is_some_and(|a| a.unlocked)
+    }
+
+    /// Returns `true` if the achievement was locked until now.
+    pub fn unlock(&mut self, id: &str) -> bool {
+        match self.achievements.iter_mut().find(|a| a.id == id) {
+            Some(a) if !a.unlocked => {
+                a.unlocked = true;
+                true
+            }
+            Some(_) => false,
+            None => {
+                warn!("Unknown achievement \"{}\"", id);
+                false
+            }
         }
     }
 
-    pub fn unlock(&mut self) {
-        self.unlocked = true;
+    pub fn all_unlocked(&self) -> bool {
+        self.achievements.iter().all(|a| a.unlocked)
+    }
+
+    pub fn bsod_message(&self, id: &str) -> String {
+        self.get(id)
+            .map_or(id, |a| a.bsod_message.as_deref().unwrap_or(&a.name))
+            .to_owned()
     }
+}
 
-    pub fn draw(&self, position: Vec2) {
-        let rect_width = screen_width() - 100.;
+#[derive(Clone, DeRon)]
+pub struct Achievement {
+    pub id: String,
+    pub name: String,
+    pub desc: String,
+    /// File under `assets/images`
+    pub icon: String,
+    #[nserde(default)]
+    pub hidden: bool,
+    #[nserde(default)]
+    pub bsod_message: Option<String>,
+    #[nserde(skip)]
+    pub unlocked: bool,
+}
+
+impl Achievement {
+    pub fn draw(&self, position: Vec2, rect_width: f32, texture: Texture2D) {
         let rect_height = 42.;
         let img_width = 32.;
         let img_height = 32.;
@@ -92,7 +96,7 @@ impl Achievement {
         );
 
         draw_texture_ex(
-            self.texture,
+            texture,
             position.x + padding,
             position.y + padding,
             WHITE,
@@ -110,8 +114,12 @@ impl Achievement {
         draw_text(
             &format!(
                 "{} : {}",
-                self.name,
-                if self.unlocked { self.desc } else { "???" }
+                if self.hidden && !self.unlocked {
+                    "???"
+                } else {
+                    &self.name
+                },
+                if self.unlocked { &self.desc } else { "???" }
             ),
             position.x + img_width + padding,
             position.y + (rect_height / 2.),
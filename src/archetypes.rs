@@ -0,0 +1,344 @@
+use nanoserde::DeRon;
+
+use crate::{
+    events::ItemKind,
+    resources::embedded_image,
+    rng::{fnv1a, Rng},
+    steering::SteeringParams,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+const ARCHETYPES_PATH: &str = "assets/enemies.ron";
+/// The followers the Kernel sends at the player
+const SWARM: &str = "swarm";
+
+/// How an archetype moves and attacks, see the matching `EntityType`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, DeRon)]
+pub enum Behavior {
+    Bullet,
+    Follower,
+    Pather,
+    Splitter,
+    Orbiter,
+}
+
+impl Behavior {
+    /// The Kernel, splitters and orbiters spawn the first archetype with
+    /// these behaviors, so the file needs at least one of each.
+    const REQUIRED: [Self; 2] = [Self::Bullet, Self::Pather];
+}
+
+#[derive(Clone, Debug, DeRon)]
+pub struct ItemDrop {
+    pub item: ItemKind,
+    /// In percent
+    pub chance: f32,
+}
+
+/// One kind of enemy, as defined in `assets/enemies.ron`.
+#[derive(Clone, Debug, DeRon)]
+pub struct Archetype {
+    pub id: String,
+    pub behavior: Behavior,
+    pub radius: f32,
+    /// World units per tick, the top speed for followers and orbiters
+    pub speed: f32,
+    /// Power hits needed to destroy it
+    #[nserde(default)]
+    pub hp: Option<u32>,
+    /// Files under `assets/images`
+    pub texture: String,
+    pub glitch_texture: String,
+    /// Rolled once when it dies, at most one item drops
+    #[nserde(default)]
+    pub drops: Vec<ItemDrop>,
+    /// Ticks between regular spawns, never spawned on its own if missing
+    #[nserde(default)]
+    pub spawn_every: Option<u32>,
+    /// Followers only, their top speed is `speed`
+    #[nserde(default)]
+    pub steering: Option<SteeringParams>,
+}
+
+/// What an entity needs to know about its archetype.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Stats {
+    /// Index in `Archetypes::archetypes`
+    pub archetype: usize,
+    pub behavior: Behavior,
+    pub radius: f32,
+    pub speed: f32,
+    pub hp: u32,
+    /// Set for followers
+    pub steering: Option<SteeringParams>,
+}
+
+#[derive(Clone)]
+pub struct Archetypes {
+    pub archetypes: Vec<Archetype>,
+    /// Of the file they were read from, replays only play with the same
+    pub hash: u64,
+}
+
+impl Archetypes {
+    /// Definitions from `assets/enemies.ron`. Desktop builds read the file
+    /// from the working directory first, so it can be tuned without
+    /// recompiling, and keep the copy built into the game if it's missing or
+    /// invalid.
+    pub fn load() -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Ok(ron) = std::fs::read_to_string(ARCHETYPES_PATH) {
+            match Self::from_ron(&ron) {
+                Ok(archetypes) => return archetypes,
+                Err(err) => macroquad::logging::warn!(
+                    "Ignoring {}, using the built-in enemies: {}",
+                    ARCHETYPES_PATH,
+                    err
+                ),
+            }
+        }
+
+        Self::from_ron(include_str!("../assets/enemies.ron"))
+            .unwrap_or_else(|err| panic!("assets/enemies.ron: {err}"))
+    }
+
+    pub fn from_ron(ron: &str) -> Result<Self, String> {
+        let archetypes = Vec::<Archetype>::deserialize_ron(ron).map_err(|e| e.to_string())?;
+
+        for (i, a) in archetypes.iter().enumerate() {
+            let name = format!("enemy #{i} \"{}\"", a.id);
+            if a.id.is_empty() {
+                return Err(format!("enemy #{i} has an empty id"));
+            }
+            if archetypes[..i].iter().any(|other| other.id == a.id) {
+                return Err(format!("duplicate enemy id \"{}\"", a.id));
+            }
+            if !a.radius.is_finite() || a.radius <= 0. {
+                return Err(format!("{name}: radius must be positive, got {}", a.radius));
+            }
+            if !a.speed.is_finite() || a.speed < 0. {
+                return Err(format!("{name}: speed can't be negative, got {}", a.speed));
+            }
+            if a.hp == Some(0) {
+                return Err(format!("{name}: hp must be at least 1"));
+            }
+            for texture in [&a.texture, &a.glitch_texture] {
+                if embedded_image(texture).is_none() {
+                    return Err(format!("{name}: unknown image \"{texture}\""));
+                }
+            }
+            let total: f32 = a.drops.iter().map(|d| d.chance).sum();
+            if a.drops
+                .iter()
+                .any(|d| !d.chance.is_finite() || d.chance < 0.)
+                || total > 100.
+            {
+                return Err(format!(
+                    "{name}: drop chances must be positive and add up to 100 at most"
+                ));
+            }
+            if a.spawn_every == Some(0) {
+                return Err(format!("{name}: spawn_every must be at least 1 tick"));
+            }
+            match (a.behavior, &a.steering) {
+                (Behavior::Follower, None) => {
+                    return Err(format!("{name}: followers need steering"));
+                }
+                (Behavior::Follower, Some(s)) => {
+                    let positive = [s.max_force, s.neighbor_radius, s.separation_radius];
+                    let weights = [
+                        s.seek,
+                        s.arrive_radius,
+                        s.lead,
+                        s.separation,
+                        s.alignment,
+                        s.cohesion,
+                    ];
+                    if positive.iter().any(|v| !v.is_finite() || *v <= 0.)
+                        || weights.iter().any(|v| !v.is_finite() || *v < 0.)
+                        || !s.flank.is_finite()
+                    {
+                        return Err(format!(
+                            "{name}: steering max_force and radii must be positive, \
+                             the other fields can't be negative except flank"
+                        ));
+                    }
+                }
+                (_, Some(_)) => return Err(format!("{name}: only followers have steering")),
+                (_, None) => (),
+            }
+        }
+
+        for behavior in Behavior::REQUIRED {
+            if !archetypes.iter().any(|a| a.behavior == behavior) {
+                return Err(format!("no enemy with behavior {behavior:?}"));
+            }
+        }
+        if !archetypes
+            .iter()
+            .any(|a| a.id == SWARM && a.behavior == Behavior::Follower)
+        {
+            return Err(format!("no Follower with id \"{SWARM}\""));
+        }
+
+        Ok(Self {
+            archetypes,
+            hash: fnv1a(ron.as_bytes()),
+        })
+    }
+
+    pub const fn len(&self) -> usize {
+        self.archetypes.len()
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.archetypes.is_empty()
+    }
+
+    pub fn stats(&self, archetype: usize) -> Stats {
+        let a = &self.archetypes[archetype];
+        Stats {
+            archetype,
+            behavior: a.behavior,
+            radius: a.radius,
+            speed: a.speed,
+            hp: a.hp.unwrap_or(1),
+            steering: a.steering.map(|steering| SteeringParams {
+                max_speed: a.speed,
+                ..steering
+            }),
+        }
+    }
+
+    /// Stats of the `swarm` follower, spawned by the Kernel.
+    pub fn swarm(&self) -> Stats {
+        let archetype = self
+            .archetypes
+            .iter()
+            .position(|a| a.id == SWARM)
+            .expect("checked by from_ron");
+        self.stats(archetype)
+    }
+
+    /// Stats of the first archetype with `behavior`, for enemies spawned by
+    /// other enemies.
+    pub fn base(&self, behavior: Behavior) -> Stats {
+        let archetype = self
+            .archetypes
+            .iter()
+            .position(|a| a.behavior == behavior)
+            .expect("checked by from_ron");
+        self.stats(archetype)
+    }
+
+    /// The item dropped by a dead enemy of that archetype, if any.
+    pub fn roll_drop(&self, archetype: usize, rng: &mut Rng) -> Option<ItemKind> {
+        let drops = &self.archetypes[archetype].drops;
+        if drops.is_empty() {
+            return None;
+        }
+
+        let mut roll = rng.gen_range(0., 100.);
+        drops.iter().find_map(|d| {
+            roll -= d.chance;
+            (roll < 0.).then_some(d.item)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::archetypes;
+
+    const STEERING: &str = "(max_force: 0.01, seek: 1, neighbor_radius: 4, separation: 1, \
+                            separation_radius: 1, alignment: 0.5, cohesion: 0.5)";
+
+    fn enemy(id: &str, behavior: &str, extra: &str) -> String {
+        format!(
+            "(id: \"{id}\", behavior: {behavior}, radius: 0.5, speed: 0.2, \
+             texture: \"bullet.png\", glitch_texture: \"bullet_glitch.png\", {extra}),"
+        )
+    }
+
+    /// The smallest valid file, with `extra` added to the end.
+    fn ron(extra: &str) -> String {
+        format!(
+            "[{}{}{}{extra}]",
+            enemy("bullet", "Bullet", ""),
+            enemy("swarm", "Follower", &format!("steering: {STEERING}")),
+            enemy("pather", "Pather", ""),
+        )
+    }
+
+    fn error(ron: &str) -> String {
+        Archetypes::from_ron(ron).err().unwrap_or_default()
+    }
+
+    #[test]
+    fn shipped_enemies_are_valid() {
+        let archetypes = archetypes();
+        let swarm = archetypes.swarm();
+
+        assert_eq!(swarm.behavior, Behavior::Follower);
+        assert_eq!(swarm.steering.map(|s| s.max_speed), Some(swarm.speed));
+    }
+
+    #[test]
+    fn hashes_the_file() {
+        let a = Archetypes::from_ron(&ron("")).unwrap();
+        let b = Archetypes::from_ron(&ron(&enemy("extra", "Bullet", ""))).unwrap();
+
+        assert_eq!(a.len(), 3);
+        assert_eq!(a.hash, Archetypes::from_ron(&ron("")).unwrap().hash);
+        assert_ne!(a.hash, b.hash);
+    }
+
+    #[test]
+    fn rejects_invalid_enemies() {
+        for (extra, expected) in [
+            (enemy("", "Bullet", ""), "empty id"),
+            (enemy("bullet", "Bullet", ""), "duplicate enemy id"),
+            (enemy("x", "Bullet", "hp: 0"), "hp must be at least 1"),
+            (enemy("x", "Bullet", "spawn_every: 0"), "spawn_every"),
+            (
+                enemy(
+                    "x",
+                    "Bullet",
+                    "drops: [(item: Heal, chance: 60), (item: Mana, chance: 60)]",
+                ),
+                "drop chances",
+            ),
+            (enemy("x", "Follower", ""), "followers need steering"),
+            (
+                enemy("x", "Bullet", &format!("steering: {STEERING}")),
+                "only followers have steering",
+            ),
+            (
+                enemy(
+                    "x",
+                    "Follower",
+                    &format!("steering: {}", STEERING.replace("0.01", "0")),
+                ),
+                "steering max_force",
+            ),
+        ] {
+            let err = error(&ron(&extra));
+            assert!(err.contains(expected), "{extra}: {err}");
+        }
+
+        let unknown = ron("").replace("\"bullet.png\"", "\"missing.png\"");
+        assert!(error(&unknown).contains("unknown image"));
+        let negative = ron("").replacen("speed: 0.2", "speed: -1", 1);
+        assert!(error(&negative).contains("speed"));
+    }
+
+    #[test]
+    fn needs_the_enemies_other_enemies_spawn() {
+        let no_pather = ron("").replace("Pather", "Bullet");
+        let no_swarm = ron("").replace("\"swarm\"", "\"follower\"");
+
+        assert!(error(&no_pather).contains("Pather"));
+        assert!(error(&no_swarm).contains("swarm"));
+    }
+}
@@ -0,0 +1,414 @@
+// Developer console, toggled with the backtick key. Its commands act on the
+// world, the achievements and the frontend, so the bug hunt can be tuned and
+// tested without editing constants and recompiling. The inputs alone can't
+// replay a cheated run, so its recording isn't saved.
+
+use macroquad::prelude::*;
+
+use crate::{
+    entities::{random_outside_pos, Entity},
+    events::Event,
+    profile::Profile,
+    world::World,
+};
+
+const FONT_SIZE: f32 = 20.;
+const LINE_HEIGHT: f32 = 20.;
+/// Lines of output kept
+const SCROLLBACK: usize = 100;
+/// Part of the screen covered when open
+const HEIGHT: f32 = 0.4;
+/// Most enemies spawned by one command
+const MAX_SPAWN: u32 = 500;
+/// Highest hp and mana, a pickup past it overflows
+const MAX_STAT: u32 = 3;
+
+const HELP: &[&str] = &[
+    "spawn <enemy> [count]     spawn up to 500 enemies from assets/enemies.ron",
+    "set <hp|mana|instability> <value>  hp and mana go up to 3",
+    "unlock <achievement>      unlock without crashing",
+    "reset_achievements        lock everything again",
+    "timescale <factor>        1 is normal speed",
+    "godmode                   toggle taking no damage",
+    "seed <n>                  restart the run with that seed",
+    "bsod <message>            crash the computer",
+    "clear",
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Field {
+    Hp,
+    Mana,
+    Instability,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Command {
+    Help,
+    Clear,
+    /// `Archetype::id`, how many
+    Spawn(String, u32),
+    Set(Field, u32),
+    /// `Achievement::id`
+    Unlock(String),
+    ResetAchievements,
+    Timescale(f32),
+    Godmode,
+    Seed(u64),
+    Bsod(String),
+}
+
+fn number<T: std::str::FromStr>(arg: Option<&str>, what: &str) -> Result<T, String> {
+    let arg = arg.ok_or_else(|| format!("missing {what}"))?;
+    arg.parse()
+        .map_err(|_| format!("{what} must be a number, got \"{arg}\""))
+}
+
+impl Command {
+    /// Whether the run no longer plays out from its seed and inputs alone.
+    pub const fn cheats(&self) -> bool {
+        matches!(
+            self,
+            Self::Spawn(..)
+                | Self::Set(..)
+                | Self::Unlock(_)
+                | Self::ResetAchievements
+                | Self::Timescale(_)
+                | Self::Godmode
+                | Self::Bsod(_)
+        )
+    }
+
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let mut words = line.split_whitespace();
+        let Some(name) = words.next() else {
+            return Err("empty command".to_owned());
+        };
+
+        let command = match name {
+            "help" => Self::Help,
+            "clear" => Self::Clear,
+            "spawn" => {
+                let enemy = words.next().ok_or("missing enemy")?.to_owned();
+                let count = match words.next() {
+                    Some(count) => number(Some(count), "count")?,
+                    None => 1,
+                };
+                if count > MAX_SPAWN {
+                    return Err(format!("can't spawn more than {MAX_SPAWN} at once"));
+                }
+                Self::Spawn(enemy, count)
+            }
+            "set" => {
+                let field = match words.next() {
+                    Some("hp") => Field::Hp,
+                    Some("mana") => Field::Mana,
+                    Some("instability") => Field::Instability,
+                    Some(other) => return Err(format!("can't set \"{other}\"")),
+                    None => return Err("missing field".to_owned()),
+                };
+                let value = number(words.next(), "value")?;
+                if field != Field::Instability && value > MAX_STAT {
+                    return Err(format!("hp and mana go up to {MAX_STAT}"));
+                }
+                Self::Set(field, value)
+            }
+            "unlock" => Self::Unlock(words.next().ok_or("missing achievement")?.to_owned()),
+            "reset_achievements" => Self::ResetAchievements,
+            "timescale" => {
+                let factor: f32 = number(words.next(), "factor")?;
+                if !factor.is_finite() || factor < 0. {
+                    return Err("factor can't be negative".to_owned());
+                }
+                Self::Timescale(factor)
+            }
+            "godmode" => Self::Godmode,
+            "seed" => Self::Seed(number(words.next(), "seed")?),
+            "bsod" => {
+                let message = line.trim_start()["bsod".len()..].trim();
+                if message.is_empty() {
+                    return Err("missing message".to_owned());
+                }
+                return Ok(Self::Bsod(message.to_owned()));
+            }
+            other => return Err(format!("unknown command \"{other}\", try help")),
+        };
+
+        words
+            .next()
+            .map_or(Ok(command), |extra| Err(format!("unexpected \"{extra}\"")))
+    }
+}
+
+pub struct Console {
+    pub open: bool,
+    input: String,
+    output: Vec<String>,
+    /// Commands entered, oldest first
+    history: Vec<String>,
+    /// Index in `history` while browsing it with the arrows
+    browsing: Option<usize>,
+    /// Simulation speed multiplier
+    pub timescale: f32,
+}
+
+impl Console {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            input: String::new(),
+            output: vec!["Dinwows debug console, type help".to_owned()],
+            history: Vec::new(),
+            browsing: None,
+            timescale: 1.,
+        }
+    }
+
+    fn print(&mut self, line: impl Into<String>) {
+        self.output.push(line.into());
+        let excess = self.output.len().saturating_sub(SCROLLBACK);
+        self.output.drain(..excess);
+    }
+
+    /// Handles the keyboard for this frame. Returns the command entered, once
+    /// it ran.
+    pub fn update(
+        &mut self,
+        world: &mut World,
+        profile: &mut Profile,
+        events: &mut Vec<Event>,
+    ) -> Option<Command> {
+        if is_key_pressed(KeyCode::GraveAccent) {
+            self.open = !self.open;
+        }
+        if !self.open {
+            return None;
+        }
+
+        while let Some(c) = get_char_pressed() {
+            if !c.is_control() && c != '`' {
+                self.input.push(c);
+            }
+        }
+        if is_key_pressed(KeyCode::Backspace) {
+            self.input.pop();
+        }
+        if is_key_pressed(KeyCode::Up) && !self.history.is_empty() {
+            let i = self
+                .browsing
+                .map_or(self.history.len() - 1, |i| i.saturating_sub(1));
+            self.browsing = Some(i);
+            self.input.clone_from(&self.history[i]);
+        }
+        if is_key_pressed(KeyCode::Down) {
+            if let Some(i) = self.browsing {
+                self.browsing = (i + 1 < self.history.len()).then_some(i + 1);
+                self.input = self
+                    .browsing
+                    .map_or_else(String::new, |i| self.history[i].clone());
+            }
+        }
+        if !is_key_pressed(KeyCode::Enter) || self.input.trim().is_empty() {
+            return None;
+        }
+
+        let line = std::mem::take(&mut self.input);
+        self.browsing = None;
+        self.print(format!("> {line}"));
+        self.history.push(line.clone());
+
+        match Command::parse(&line) {
+            Ok(command) => {
+                let result = self.execute(&command, world, profile, events);
+                if let Some(result) = result {
+                    self.print(result);
+                }
+                Some(command)
+            }
+            Err(err) => {
+                self.print(format!("error: {err}"));
+                None
+            }
+        }
+    }
+
+    /// Returns what to print.
+    fn execute(
+        &mut self,
+        command: &Command,
+        world: &mut World,
+        profile: &mut Profile,
+        events: &mut Vec<Event>,
+    ) -> Option<String> {
+        Some(match command {
+            Command::Help => {
+                for line in HELP {
+                    self.print(*line);
+                }
+                return None;
+            }
+            Command::Clear => {
+                self.output.clear();
+                return None;
+            }
+            Command::Spawn(enemy, count) => {
+                let Some(archetype) = world
+                    .archetypes
+                    .archetypes
+                    .iter()
+                    .position(|a| &a.id == enemy)
+                else {
+                    return Some(format!("error: unknown enemy \"{enemy}\""));
+                };
+                let stats = world.archetypes.stats(archetype);
+                for _ in 0..*count {
+                    let pos = random_outside_pos(&mut world.rng);
+                    let enemy = Entity::spawn(stats, pos, world.player.pos, &mut world.rng);
+                    world.enemies.push(enemy);
+                }
+                format!("spawned {count} {enemy}")
+            }
+            Command::Set(field, value) => {
+                // `parse` keeps hp and mana within `MAX_STAT`
+                match field {
+                    Field::Hp => world.hp = *value as u8,
+                    Field::Mana => world.mana = *value as u8,
+                    Field::Instability => world.instability = *value,
+                }
+                format!("{field:?} = {value}")
+            }
+            Command::Unlock(id) => {
+                if world.achievements.get(id).is_none() {
+                    return Some(format!("error: unknown achievement \"{id}\""));
+                }
+                if world.bug_found(id, events) {
+                    format!("unlocked {id}")
+                } else {
+                    format!("{id} was already unlocked")
+                }
+            }
+            Command::ResetAchievements => {
+                for achievement in &mut world.achievements.achievements {
+                    achievement.unlocked = false;
+                }
+                profile.unlocked.clear();
+                profile.save();
+                "all achievements locked".to_owned()
+            }
+            Command::Timescale(factor) => {
+                self.timescale = *factor;
+                format!("timescale {factor}")
+            }
+            Command::Godmode => {
+                world.godmode = !world.godmode;
+                format!("godmode {}", if world.godmode { "on" } else { "off" })
+            }
+            Command::Seed(seed) => {
+                world.seed = *seed;
+                world.reset();
+                format!("restarted with seed {seed}")
+            }
+            Command::Bsod(message) => {
+                self.open = false;
+                world.bsod(message, events);
+                return None;
+            }
+        })
+    }
+
+    pub fn draw(&self) {
+        if !self.open {
+            return;
+        }
+
+        let height = screen_height() * HEIGHT;
+        draw_rectangle(0., 0., screen_width(), height, Color::new(0., 0., 0., 0.85));
+        draw_line(0., height, screen_width(), height, 2., GREEN);
+
+        let prompt_y = height - 10.;
+        let cursor = if ((get_time() * 2.) as u32).is_multiple_of(2) {
+            "_"
+        } else {
+            ""
+        };
+        draw_text(
+            &format!("> {}{cursor}", self.input),
+            10.,
+            prompt_y,
+            FONT_SIZE,
+            GREEN,
+        );
+
+        let rows = ((prompt_y - LINE_HEIGHT) / LINE_HEIGHT) as usize;
+        let mut y = prompt_y - LINE_HEIGHT;
+        for line in self.output.iter().rev().take(rows) {
+            let color = if line.starts_with("error") {
+                RED
+            } else {
+                LIGHTGRAY
+            };
+            draw_text(line, 10., y, FONT_SIZE, color);
+            y -= LINE_HEIGHT;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_commands_and_their_arguments() {
+        assert_eq!(
+            Command::parse("spawn follower"),
+            Ok(Command::Spawn("follower".to_owned(), 1))
+        );
+        assert_eq!(
+            Command::parse("  spawn pather 20 "),
+            Ok(Command::Spawn("pather".to_owned(), 20))
+        );
+        assert_eq!(
+            Command::parse("set mana 2"),
+            Ok(Command::Set(Field::Mana, 2))
+        );
+        assert_eq!(
+            Command::parse("set instability 40"),
+            Ok(Command::Set(Field::Instability, 40))
+        );
+        assert_eq!(Command::parse("timescale 0.5"), Ok(Command::Timescale(0.5)));
+        assert_eq!(Command::parse("seed 42"), Ok(Command::Seed(42)));
+        assert_eq!(
+            Command::parse("bsod  out of   memory"),
+            Ok(Command::Bsod("out of   memory".to_owned()))
+        );
+    }
+
+    #[test]
+    fn rejects_bad_commands() {
+        for line in [
+            "",
+            "fly",
+            "spawn",
+            "spawn follower many",
+            "spawn follower 501",
+            "set",
+            "set score 3",
+            "set hp 4",
+            "set mana 255",
+            "timescale -1",
+            "timescale NaN",
+            "godmode on",
+            "bsod",
+        ] {
+            assert!(Command::parse(line).is_err(), "{line:?}");
+        }
+    }
+
+    #[test]
+    fn only_world_changes_cheat() {
+        assert!(Command::parse("set hp 1").unwrap().cheats());
+        assert!(Command::parse("godmode").unwrap().cheats());
+        assert!(!Command::parse("seed 3").unwrap().cheats());
+        assert!(!Command::parse("help").unwrap().cheats());
+    }
+}
@@ -0,0 +1,198 @@
+// The Debugger desktop app: a live view of the running `World` with
+// pause and single-step controls. In the story it's the player's tool to hunt
+// bugs, for us it's the inspector used while tuning.
+
+use macroquad::prelude::*;
+
+use crate::{entities::EntityType, timestep::TICK_RATE, world::World};
+
+const FONT_SIZE: f32 = 18.;
+const LINE_HEIGHT: f32 = 18.;
+const PADDING: f32 = 10.;
+const BUTTON_SIZE: Vec2 = Vec2::new(90., 26.);
+/// Left edge of each column of the enemy table, from the window's left
+const COLUMNS: [f32; 7] = [0., 40., 130., 200., 270., 340., 410.];
+
+#[derive(Default)]
+pub struct Debugger {
+    /// The game doesn't tick on its own
+    pub paused: bool,
+    /// A single tick was asked for while paused
+    step: bool,
+    /// First row of the enemy table
+    scroll: usize,
+}
+
+impl Debugger {
+    /// Whether the paused game should run one tick now.
+    pub fn take_step(&mut self) -> bool {
+        std::mem::take(&mut self.step)
+    }
+
+    /// The app content. `interactive` is `false` while another window or a
+    /// dialog has the mouse.
+    pub fn draw(&mut self, world: &World, rect: Rect, interactive: bool) {
+        let mouse = Vec2::from(mouse_position());
+        let clicked = interactive && is_mouse_button_pressed(MouseButton::Left);
+
+        let pause = Rect::new(
+            rect.x + PADDING,
+            rect.y + PADDING,
+            BUTTON_SIZE.x,
+            BUTTON_SIZE.y,
+        );
+        let step = Rect::new(pause.right() + 8., pause.y, BUTTON_SIZE.x, BUTTON_SIZE.y);
+        button(pause, if self.paused { "Resume" } else { "Pause" }, true);
+        button(step, "Step", self.paused);
+        if clicked && pause.contains(mouse) {
+            self.paused = !self.paused;
+        } else if clicked && self.paused && step.contains(mouse) {
+            self.step = true;
+        }
+        draw_text(
+            &format!("tick {}", (world.timer * TICK_RATE).round() as u64),
+            step.right() + 16.,
+            step.y + 19.,
+            FONT_SIZE,
+            if self.paused { YELLOW } else { GREEN },
+        );
+
+        let x = rect.x + PADDING;
+        let mut y = pause.bottom() + PADDING + LINE_HEIGHT;
+        for line in state_lines(world) {
+            draw_text(&line, x, y, FONT_SIZE, WHITE);
+            y += LINE_HEIGHT;
+        }
+
+        y += LINE_HEIGHT / 2.;
+        let headers = ["#", "type", "x", "y", "vx", "vy", "clone"];
+        for (header, column) in headers.iter().zip(COLUMNS) {
+            draw_text(header, x + column, y, FONT_SIZE, GRAY);
+        }
+        y += LINE_HEIGHT;
+
+        let rows = ((rect.bottom() - y) / LINE_HEIGHT).max(0.) as usize;
+        if interactive && rect.contains(mouse) {
+            let wheel = mouse_wheel().1;
+            if wheel < 0. {
+                self.scroll += 3;
+            } else if wheel > 0. {
+                self.scroll = self.scroll.saturating_sub(3);
+            }
+        }
+        self.scroll = self.scroll.min(world.enemies.len().saturating_sub(rows));
+
+        for (i, enemy) in world
+            .enemies
+            .iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(rows)
+        {
+            let kind = match enemy.e_type {
+                EntityType::Player => "player",
+                EntityType::Item { .. } => "item",
+                _ => &world.archetypes.archetypes[enemy.archetype].id,
+            };
+            let cells = [
+                i.to_string(),
+                kind.to_owned(),
+                format!("{:.2}", enemy.pos.x),
+                format!("{:.2}", enemy.pos.y),
+                format!("{:.3}", enemy.speed.x),
+                format!("{:.3}", enemy.speed.y),
+                if enemy.is_clone { "yes" } else { "" }.to_owned(),
+            ];
+            let color = if enemy.is_clone { MAGENTA } else { LIGHTGRAY };
+            for (cell, column) in cells.iter().zip(COLUMNS) {
+                draw_text(cell, x + column, y, FONT_SIZE, color);
+            }
+            y += LINE_HEIGHT;
+        }
+    }
+}
+
+/// Greyed out if disabled.
+fn button(rect: Rect, label: &str, enabled: bool) {
+    draw_rectangle(
+        rect.x,
+        rect.y,
+        rect.w,
+        rect.h,
+        if enabled { LIGHTGRAY } else { DARKGRAY },
+    );
+    draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 2., WHITE);
+    draw_text(
+        label,
+        rect.x + 10.,
+        rect.y + 19.,
+        FONT_SIZE + 2.,
+        if enabled { BLACK } else { GRAY },
+    );
+}
+
+/// Player, director and glitch state.
+fn state_lines(world: &World) -> Vec<String> {
+    let director = &world.director;
+    let wave = director.current_wave();
+    let glitch = world.glitches.progress(world.instability).map_or_else(
+        || "none".to_owned(),
+        |(name, elapsed, duration)| format!("{name} {elapsed}/{duration}"),
+    );
+    let flags = world.glitch_flags;
+
+    let mut lines = vec![
+        format!(
+            "hp {}  mana {}  instability {}  score {}",
+            world.hp, world.mana, world.instability, world.score
+        ),
+        format!(
+            "time {:.1}s  seed {}  enemies {}  items {}",
+            world.timer,
+            world.seed,
+            world.enemies.len(),
+            world.items.len()
+        ),
+        format!(
+            "player ({:.2}, {:.2})  speed ({:.3}, {:.3})",
+            world.player.pos.x, world.player.pos.y, world.player.speed.x, world.player.speed.y
+        ),
+        format!("glitch {glitch}"),
+        format!(
+            "flags inverted {}  sprite shift {}  hud {}  frozen {}",
+            flags.inverted_controls, flags.sprite_shift, flags.hud_corrupted, flags.frozen
+        ),
+        format!(
+            "wave #{} {} {:.1}/{:.1}s  density {:.2}  mercy {:.2}",
+            director.wave,
+            wave.name,
+            director.wave_time,
+            wave.duration,
+            director.density,
+            director.mercy
+        ),
+    ];
+
+    let timers: Vec<String> = wave
+        .spawns
+        .iter()
+        .zip(&director.progress)
+        .map(|(spawn, progress)| format!("{} {progress:.1}/{:.1}s", spawn.enemy, spawn.every))
+        .chain(
+            world
+                .archetypes
+                .archetypes
+                .iter()
+                .zip(&director.ambient)
+                .filter_map(|(a, ticks)| {
+                    a.spawn_every
+                        .map(|every| format!("{} {ticks:.0}/{every}", a.id))
+                }),
+        )
+        .collect();
+    for chunk in timers.chunks(3) {
+        lines.push(format!("spawns {}", chunk.join("  ")));
+    }
+
+    lines
+}
@@ -0,0 +1,672 @@
+// Window manager of Dinwows. Apps are movable, resizable and minimizable
+// windows stacked over the wallpaper, with a taskbar listing them next to the
+// start menu and the tray. Apps draw their own content inside
+// `Window::content`, the manager draws the frames and handles the mouse.
+
+use std::collections::VecDeque;
+
+use macroquad::prelude::*;
+
+pub const TITLE_HEIGHT: f32 = 32.;
+pub const TASKBAR_HEIGHT: f32 = 40.;
+const BORDER: f32 = 2.;
+const BUTTON_SIZE: f32 = 24.;
+/// Corner grabbed to resize a window
+const RESIZE_HANDLE: f32 = 14.;
+const MIN_SIZE: Vec2 = Vec2::new(240., 160.);
+/// Offset between windows opened one after the other
+const CASCADE: f32 = 30.;
+/// Part of a window kept on screen when it's dragged away
+const KEEP_VISIBLE: f32 = 60.;
+const TASKBAR_BUTTON_WIDTH: f32 = 160.;
+const START_BUTTON_WIDTH: f32 = 90.;
+const MENU_WIDTH: f32 = 220.;
+const MENU_ITEM_HEIGHT: f32 = 40.;
+/// Clock on the right of the taskbar
+const TRAY_WIDTH: f32 = 120.;
+const TOAST_SIZE: Vec2 = Vec2::new(320., 64.);
+/// Seconds a notification stays on screen
+const TOAST_DURATION: f32 = 4.;
+/// Older notifications are dropped beyond that
+const MAX_TOASTS: usize = 4;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum App {
+    Unglitched,
+    Achievements,
+    Replay,
+    Debugger,
+    Settings,
+}
+
+impl App {
+    pub const fn title(self) -> &'static str {
+        match self {
+            Self::Unglitched => "Unglitched",
+            Self::Achievements => "Achievements",
+            Self::Replay => "Replay",
+            Self::Debugger => "Debugger",
+            Self::Settings => "Settings",
+        }
+    }
+
+    /// Size of the window when it opens, as a part of the screen.
+    const fn default_size(self) -> Vec2 {
+        match self {
+            Self::Unglitched => Vec2::new(0.75, 0.8),
+            Self::Achievements => Vec2::new(0.6, 0.7),
+            Self::Replay => Vec2::new(0.5, 0.55),
+            Self::Debugger => Vec2::new(0.55, 0.75),
+            Self::Settings => Vec2::new(0.45, 0.75),
+        }
+    }
+}
+
+/// Entries of the start menu, top to bottom.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MenuItem {
+    Launch(App),
+    ShutDown,
+}
+
+impl MenuItem {
+    const ALL: [Self; 6] = [
+        Self::Launch(App::Unglitched),
+        Self::Launch(App::Achievements),
+        Self::Launch(App::Replay),
+        Self::Launch(App::Settings),
+        Self::Launch(App::Debugger),
+        Self::ShutDown,
+    ];
+
+    const fn label(self) -> &'static str {
+        match self {
+            Self::Launch(app) => app.title(),
+            Self::ShutDown => "Shut Down",
+        }
+    }
+}
+
+/// What a click on the windows or the taskbar asks of the frontend.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    /// The window was closed with its cross
+    Closed(App),
+    /// Picked in the start menu, same as a click on its desktop icon
+    Launch(App),
+    ShutDown,
+}
+
+#[derive(Clone, Debug)]
+pub struct Window {
+    pub app: App,
+    /// Title bar included
+    pub rect: Rect,
+    pub minimized: bool,
+    /// Value of `WindowManager::opened` when it opened, orders the taskbar
+    opened: u32,
+}
+
+impl Window {
+    /// Where the app draws, below the title bar.
+    pub fn content(&self) -> Rect {
+        Rect::new(
+            self.rect.x,
+            self.rect.y + TITLE_HEIGHT,
+            self.rect.w,
+            self.rect.h - TITLE_HEIGHT,
+        )
+    }
+
+    fn title_bar(&self) -> Rect {
+        Rect::new(self.rect.x, self.rect.y, self.rect.w, TITLE_HEIGHT)
+    }
+
+    fn close_button(&self) -> Rect {
+        let margin = (TITLE_HEIGHT - BUTTON_SIZE) / 2.;
+        Rect::new(
+            self.rect.right() - margin - BUTTON_SIZE,
+            self.rect.y + margin,
+            BUTTON_SIZE,
+            BUTTON_SIZE,
+        )
+    }
+
+    fn minimize_button(&self) -> Rect {
+        let close = self.close_button();
+        Rect::new(
+            close.x - BUTTON_SIZE - 4.,
+            close.y,
+            BUTTON_SIZE,
+            BUTTON_SIZE,
+        )
+    }
+
+    fn resize_handle(&self) -> Rect {
+        Rect::new(
+            self.rect.right() - RESIZE_HANDLE,
+            self.rect.bottom() - RESIZE_HANDLE,
+            RESIZE_HANDLE,
+            RESIZE_HANDLE,
+        )
+    }
+}
+
+/// What the mouse is doing to the focused window.
+enum Drag {
+    /// Grabbed by the title bar, at that offset from the top left corner
+    Move(Vec2),
+    /// Grabbed by the resize handle, at that offset from the bottom right
+    /// corner
+    Resize(Vec2),
+}
+
+#[derive(Default)]
+pub struct WindowManager {
+    /// Back to front, the last visible one has the focus
+    windows: Vec<Window>,
+    drag: Option<Drag>,
+    /// Windows opened so far, for the cascade
+    opened: u32,
+    menu_open: bool,
+}
+
+impl WindowManager {
+    /// Open windows, back to front.
+    pub fn windows(&self) -> impl Iterator<Item = &Window> {
+        self.windows.iter()
+    }
+
+    pub fn window(&self, app: App) -> Option<&Window> {
+        self.windows.iter().find(|w| w.app == app)
+    }
+
+    pub fn is_open(&self, app: App) -> bool {
+        self.window(app).is_some()
+    }
+
+    /// Open and not minimized.
+    pub fn is_visible(&self, app: App) -> bool {
+        self.window(app).is_some_and(|w| !w.minimized)
+    }
+
+    pub fn focused(&self) -> Option<App> {
+        self.windows
+            .iter()
+            .rev()
+            .find(|w| !w.minimized)
+            .map(|w| w.app)
+    }
+
+    /// Brings the app's window to the front, opening it if needed. Returns
+    /// `true` if it wasn't open.
+    pub fn open(&mut self, app: App) -> bool {
+        if self.is_open(app) {
+            self.focus(app);
+            return false;
+        }
+
+        let screen = Vec2::new(screen_width(), screen_height() - TASKBAR_HEIGHT);
+        let size = (app.default_size() * screen).max(MIN_SIZE);
+        let cascade = (self.opened % 5) as f32 * CASCADE;
+        let pos = (Vec2::new(110., 20.) + cascade).min((screen - size).max(Vec2::ZERO));
+
+        self.windows.push(Window {
+            app,
+            rect: Rect::new(pos.x, pos.y, size.x, size.y),
+            minimized: false,
+            opened: self.opened,
+        });
+        self.opened += 1;
+        self.drag = None;
+        true
+    }
+
+    pub fn close(&mut self, app: App) {
+        self.windows.retain(|w| w.app != app);
+        self.drag = None;
+    }
+
+    pub fn close_all(&mut self) {
+        self.windows.clear();
+        self.drag = None;
+    }
+
+    /// Moves the window to the front and restores it.
+    pub fn focus(&mut self, app: App) {
+        if let Some(i) = self.windows.iter().position(|w| w.app == app) {
+            let mut window = self.windows.remove(i);
+            window.minimized = false;
+            self.windows.push(window);
+        }
+    }
+
+    pub fn minimize(&mut self, app: App) {
+        if let Some(window) = self.windows.iter_mut().find(|w| w.app == app) {
+            window.minimized = true;
+        }
+        self.drag = None;
+    }
+
+    /// Whether a click at `pos` lands on a window, the start menu or the
+    /// taskbar rather than on the desktop.
+    pub fn covers(&self, pos: Vec2) -> bool {
+        pos.y >= screen_height() - TASKBAR_HEIGHT
+            || (self.menu_open && menu_rect().contains(pos))
+            || self
+                .windows
+                .iter()
+                .any(|w| !w.minimized && w.rect.contains(pos))
+    }
+
+    /// Handles the mouse for this frame: focus, dragging, resizing, the
+    /// title bar and taskbar buttons and the start menu.
+    pub fn update(&mut self, mouse: Vec2) -> Option<Action> {
+        if !is_mouse_button_down(MouseButton::Left) {
+            self.drag = None;
+        }
+        if let Some(drag) = self.drag.take() {
+            self.apply_drag(&drag, mouse);
+            self.drag = Some(drag);
+            return None;
+        }
+        if !is_mouse_button_pressed(MouseButton::Left) {
+            return None;
+        }
+
+        // Any click closes the menu, the ones outside still go through
+        if std::mem::take(&mut self.menu_open) {
+            if let Some((item, _)) = menu_items().find(|(_, rect)| rect.contains(mouse)) {
+                return Some(match item {
+                    MenuItem::Launch(app) => Action::Launch(app),
+                    MenuItem::ShutDown => Action::ShutDown,
+                });
+            }
+            if start_button().contains(mouse) {
+                return None;
+            }
+        }
+
+        if mouse.y >= screen_height() - TASKBAR_HEIGHT {
+            self.click_taskbar(mouse);
+            return None;
+        }
+
+        let i = self
+            .windows
+            .iter()
+            .rposition(|w| !w.minimized && w.rect.contains(mouse))?;
+        let app = self.windows[i].app;
+        self.focus(app);
+        let window = self.windows.last().expect("just focused");
+
+        if window.close_button().contains(mouse) {
+            self.close(app);
+            return Some(Action::Closed(app));
+        }
+        if window.minimize_button().contains(mouse) {
+            self.minimize(app);
+        } else if window.resize_handle().contains(mouse) {
+            let corner = Vec2::new(window.rect.right(), window.rect.bottom());
+            self.drag = Some(Drag::Resize(corner - mouse));
+        } else if window.title_bar().contains(mouse) {
+            self.drag = Some(Drag::Move(mouse - window.rect.point()));
+        }
+        None
+    }
+
+    fn apply_drag(&mut self, drag: &Drag, mouse: Vec2) {
+        let Some(window) = self.windows.last_mut() else {
+            return;
+        };
+        let rect = &mut window.rect;
+        match drag {
+            Drag::Move(offset) => {
+                let pos = mouse - *offset;
+                rect.x = pos
+                    .x
+                    .clamp(KEEP_VISIBLE - rect.w, screen_width() - KEEP_VISIBLE);
+                rect.y = pos
+                    .y
+                    .clamp(0., screen_height() - TASKBAR_HEIGHT - TITLE_HEIGHT);
+            }
+            Drag::Resize(offset) => {
+                let size = (mouse + *offset - rect.point()).max(MIN_SIZE);
+                rect.w = size.x;
+                rect.h = size.y;
+            }
+        }
+    }
+
+    /// The start button toggles the menu. A window button focuses its
+    /// window, or minimizes it if it already has the focus.
+    fn click_taskbar(&mut self, mouse: Vec2) {
+        if start_button().contains(mouse) {
+            self.menu_open = true;
+            return;
+        }
+        let Some(app) = self
+            .taskbar_buttons()
+            .find(|(_, rect)| rect.contains(mouse))
+            .map(|(app, _)| app)
+        else {
+            return;
+        };
+        if self.focused() == Some(app) {
+            self.minimize(app);
+        } else {
+            self.focus(app);
+        }
+    }
+
+    /// One button per open window, in the order they were opened.
+    fn taskbar_buttons(&self) -> impl Iterator<Item = (App, Rect)> + '_ {
+        let mut windows: Vec<&Window> = self.windows.iter().collect();
+        windows.sort_by_key(|w| w.opened);
+        let y = screen_height() - TASKBAR_HEIGHT + 4.;
+        windows.into_iter().enumerate().map(move |(i, window)| {
+            let app = window.app;
+            let x = START_BUTTON_WIDTH + 16. + i as f32 * (TASKBAR_BUTTON_WIDTH + 6.);
+            (
+                app,
+                Rect::new(x, y, TASKBAR_BUTTON_WIDTH, TASKBAR_HEIGHT - 8.),
+            )
+        })
+    }
+
+    /// The frame of `window`, drawn before its content.
+    pub fn draw_frame(&self, window: &Window, cross: Texture2D) {
+        let focused = self.focused() == Some(window.app);
+        let rect = window.rect;
+
+        draw_rectangle(
+            rect.x + 6.,
+            rect.y + 6.,
+            rect.w,
+            rect.h,
+            Color::new(0., 0., 0., 0.3),
+        );
+        draw_rectangle(
+            rect.x - BORDER,
+            rect.y - BORDER,
+            rect.w + 2. * BORDER,
+            rect.h + 2. * BORDER,
+            if focused { DARKBLUE } else { GRAY },
+        );
+        draw_rectangle(rect.x, rect.y, rect.w, rect.h, BLACK);
+
+        let bar = window.title_bar();
+        draw_rectangle(
+            bar.x,
+            bar.y,
+            bar.w,
+            bar.h,
+            if focused { LIGHTGRAY } else { GRAY },
+        );
+        draw_text(
+            window.app.title(),
+            bar.x + 10.,
+            bar.y + TITLE_HEIGHT / 2. + 8.,
+            26.,
+            BLACK,
+        );
+
+        let close = window.close_button();
+        draw_texture_ex(
+            cross,
+            close.x,
+            close.y,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(close.size()),
+                ..Default::default()
+            },
+        );
+        let minimize = window.minimize_button();
+        draw_rectangle(minimize.x, minimize.y, minimize.w, minimize.h, WHITE);
+        draw_rectangle_lines(minimize.x, minimize.y, minimize.w, minimize.h, 2., BLACK);
+        draw_line(
+            minimize.x + 5.,
+            minimize.bottom() - 6.,
+            minimize.right() - 5.,
+            minimize.bottom() - 6.,
+            3.,
+            BLACK,
+        );
+    }
+
+    /// The grip of the resize handle, drawn after the content.
+    pub fn draw_grip(window: &Window) {
+        let handle = window.resize_handle();
+        for i in 1..=3 {
+            let step = i as f32 * RESIZE_HANDLE / 4.;
+            draw_line(
+                handle.right() - step,
+                handle.bottom(),
+                handle.right(),
+                handle.bottom() - step,
+                1.5,
+                LIGHTGRAY,
+            );
+        }
+    }
+
+    /// The taskbar and the start menu, the tray draws itself on top.
+    pub fn draw_taskbar(&self) {
+        let y = screen_height() - TASKBAR_HEIGHT;
+        draw_rectangle(0., y, screen_width(), TASKBAR_HEIGHT, LIGHTGRAY);
+        draw_line(0., y, screen_width(), y, 2., WHITE);
+
+        let start = start_button();
+        draw_rectangle(
+            start.x,
+            start.y,
+            start.w,
+            start.h,
+            if self.menu_open { WHITE } else { GREEN },
+        );
+        draw_rectangle_lines(start.x, start.y, start.w, start.h, 2., DARKGRAY);
+        draw_text(
+            "Start",
+            start.x + 16.,
+            start.y + start.h / 2. + 8.,
+            26.,
+            BLACK,
+        );
+
+        let focused = self.focused();
+        for (app, rect) in self.taskbar_buttons() {
+            let minimized = self.window(app).is_some_and(|w| w.minimized);
+            let color = if focused == Some(app) {
+                WHITE
+            } else if minimized {
+                GRAY
+            } else {
+                Color::new(0.85, 0.85, 0.85, 1.)
+            };
+            draw_rectangle(rect.x, rect.y, rect.w, rect.h, color);
+            draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 2., DARKGRAY);
+            draw_text(
+                app.title(),
+                rect.x + 10.,
+                rect.y + rect.h / 2. + 7.,
+                22.,
+                BLACK,
+            );
+        }
+
+        if self.menu_open {
+            let menu = menu_rect();
+            draw_rectangle(menu.x, menu.y, menu.w, menu.h, LIGHTGRAY);
+            draw_rectangle_lines(menu.x, menu.y, menu.w, menu.h, 2., DARKGRAY);
+            let mouse = Vec2::from(mouse_position());
+            for (item, rect) in menu_items() {
+                if rect.contains(mouse) {
+                    draw_rectangle(rect.x, rect.y, rect.w, rect.h, DARKBLUE);
+                }
+                if item == MenuItem::ShutDown {
+                    draw_line(rect.x, rect.y, rect.right(), rect.y, 1., GRAY);
+                }
+                draw_text(
+                    item.label(),
+                    rect.x + 14.,
+                    rect.y + rect.h / 2. + 8.,
+                    24.,
+                    if rect.contains(mouse) { WHITE } else { BLACK },
+                );
+            }
+        }
+    }
+}
+
+fn start_button() -> Rect {
+    Rect::new(
+        8.,
+        screen_height() - TASKBAR_HEIGHT + 4.,
+        START_BUTTON_WIDTH,
+        TASKBAR_HEIGHT - 8.,
+    )
+}
+
+/// Opens up from the start button.
+fn menu_rect() -> Rect {
+    let height = MenuItem::ALL.len() as f32 * MENU_ITEM_HEIGHT;
+    Rect::new(
+        0.,
+        screen_height() - TASKBAR_HEIGHT - height,
+        MENU_WIDTH,
+        height,
+    )
+}
+
+fn menu_items() -> impl Iterator<Item = (MenuItem, Rect)> {
+    let menu = menu_rect();
+    MenuItem::ALL.into_iter().enumerate().map(move |(i, item)| {
+        (
+            item,
+            Rect::new(
+                menu.x,
+                menu.y + i as f32 * MENU_ITEM_HEIGHT,
+                menu.w,
+                MENU_ITEM_HEIGHT,
+            ),
+        )
+    })
+}
+
+struct Toast {
+    title: String,
+    text: String,
+    /// Seconds left on screen
+    left: f32,
+}
+
+/// Right end of the taskbar: the clock and the notifications popping above
+/// it.
+#[derive(Default)]
+pub struct Tray {
+    /// Newest last
+    toasts: VecDeque<Toast>,
+    /// Seconds of game played this session, shown by the clock
+    pub play_time: f32,
+}
+
+impl Tray {
+    pub fn notify(&mut self, title: &str, text: &str) {
+        self.toasts.push_back(Toast {
+            title: title.to_owned(),
+            text: text.to_owned(),
+            left: TOAST_DURATION,
+        });
+        if self.toasts.len() > MAX_TOASTS {
+            self.toasts.pop_front();
+        }
+    }
+
+    /// Counts down the notifications, call it only while they can be seen.
+    pub fn update(&mut self, dt: f32) {
+        for toast in &mut self.toasts {
+            toast.left -= dt;
+        }
+        self.toasts.retain(|t| t.left > 0.);
+    }
+
+    pub fn draw(&self) {
+        let y = screen_height() - TASKBAR_HEIGHT;
+        let tray = Rect::new(
+            screen_width() - TRAY_WIDTH,
+            y + 4.,
+            TRAY_WIDTH - 8.,
+            TASKBAR_HEIGHT - 8.,
+        );
+        draw_rectangle_lines(tray.x, tray.y, tray.w, tray.h, 2., GRAY);
+        let seconds = self.play_time as u32;
+        draw_text(
+            &format!(
+                "{}:{:02}:{:02}",
+                seconds / 3600,
+                seconds / 60 % 60,
+                seconds % 60
+            ),
+            tray.x + 14.,
+            tray.y + tray.h / 2. + 8.,
+            26.,
+            BLACK,
+        );
+
+        let x = screen_width() - TOAST_SIZE.x - 8.;
+        for (i, toast) in self.toasts.iter().rev().enumerate() {
+            let top = y - (i + 1) as f32 * (TOAST_SIZE.y + 6.);
+            // Fades out during its last second
+            let alpha = toast.left.min(1.);
+            draw_rectangle(
+                x,
+                top,
+                TOAST_SIZE.x,
+                TOAST_SIZE.y,
+                Color::new(0.1, 0.1, 0.2, 0.9 * alpha),
+            );
+            draw_rectangle_lines(
+                x,
+                top,
+                TOAST_SIZE.x,
+                TOAST_SIZE.y,
+                2.,
+                Color::new(1., 1., 1., alpha),
+            );
+            draw_text(
+                &toast.title,
+                x + 12.,
+                top + 26.,
+                24.,
+                Color::new(1., 1., 0., alpha),
+            );
+            draw_text(
+                &toast.text,
+                x + 12.,
+                top + 50.,
+                20.,
+                Color::new(1., 1., 1., alpha),
+            );
+        }
+    }
+}
+
+/// Restricts drawing to `rect` until `unclip`, so content doesn't spill out
+/// of its window.
+pub fn clip(rect: Rect) {
+    let mut gl = unsafe { get_internal_gl() };
+    gl.flush();
+    gl.quad_gl.scissor(Some((
+        rect.x as i32,
+        rect.y as i32,
+        rect.w as i32,
+        rect.h as i32,
+    )));
+}
+
+pub fn unclip() {
+    let mut gl = unsafe { get_internal_gl() };
+    gl.flush();
+    gl.quad_gl.scissor(None);
+}
@@ -0,0 +1,148 @@
+use std::collections::VecDeque;
+
+use nanoserde::DeRon;
+
+use crate::events::{Event, Sfx};
+
+/// Named moments of the game that can start a dialog sequence.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, DeRon)]
+pub enum Trigger {
+    FirstLaunch,
+    NameInput,
+    FirstBsod,
+    AllBugsFound,
+}
+
+impl Trigger {
+    const ALL: [Self; 4] = [
+        Self::FirstLaunch,
+        Self::NameInput,
+        Self::FirstBsod,
+        Self::AllBugsFound,
+    ];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::FirstLaunch => "first_launch",
+            Self::NameInput => "name_input",
+            Self::FirstBsod => "first_bsod",
+            Self::AllBugsFound => "all_bugs_found",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.name() == name)
+    }
+}
+
+/// Look of a popup.
+#[derive(Clone, Debug, DeRon)]
+pub enum PopupStyle {
+    INFO,
+    WARNING,
+    ERROR,
+}
+
+impl PopupStyle {
+    pub const fn get_name(&self) -> &str {
+        match self {
+            Self::ERROR => "Error",
+            Self::WARNING => "Warning",
+            Self::INFO => "Info",
+        }
+    }
+}
+
+/// What happens once a popup is closed.
+#[derive(Clone, Debug, DeRon)]
+pub enum DialogAction {
+    Bsod(String),
+    /// Crash for good, the BSOD can't be dismissed anymore.
+    FinalBsod(String),
+}
+
+#[derive(Clone, Debug, DeRon)]
+pub struct DialogStep {
+    pub text: String,
+    pub style: PopupStyle,
+    #[nserde(default)]
+    pub sound: Option<Sfx>,
+    #[nserde(default)]
+    pub on_close: Option<DialogAction>,
+}
+
+#[derive(Clone, Debug, DeRon)]
+pub struct DialogSequence {
+    pub trigger: Trigger,
+    #[nserde(default)]
+    pub repeat: bool,
+    pub steps: Vec<DialogStep>,
+}
+
+/// Popups waiting to be shown, front first.
+pub struct DialogQueue {
+    sequences: Vec<DialogSequence>,
+    queue: VecDeque<(Trigger, DialogStep)>,
+    pub seen: Vec<Trigger>,
+}
+
+impl DialogQueue {
+    pub fn load() -> Self {
+        let sequences =
+            Vec::<DialogSequence>::deserialize_ron(include_str!("../assets/dialogs.ron"))
+                .unwrap_or_else(|err| panic!("assets/dialogs.ron: {err}"));
+
+        Self {
+            sequences,
+            queue: VecDeque::new(),
+            seen: Vec::new(),
+        }
+    }
+
+    /// Queues the sequence for `trigger`, unless it already played and isn't
+    /// meant to repeat. Returns `true` if it was queued.
+    pub fn trigger(&mut self, trigger: Trigger, events: &mut Vec<Event>) -> bool {
+        let Some(sequence) = self.sequences.iter().find(|s| s.trigger == trigger) else {
+            return false;
+        };
+        if !sequence.repeat && self.seen.contains(&trigger) {
+            return false;
+        }
+
+        let was_empty = self.queue.is_empty();
+        self.queue
+            .extend(sequence.steps.iter().map(|step| (trigger, step.clone())));
+        if !self.seen.contains(&trigger) {
+            self.seen.push(trigger);
+        }
+        if was_empty {
+            self.play_current_sound(events);
+        }
+        true
+    }
+
+    pub fn current(&self) -> Option<&DialogStep> {
+        self.queue.front().map(|(_, step)| step)
+    }
+
+    pub fn current_trigger(&self) -> Option<Trigger> {
+        self.queue.front().map(|(trigger, _)| *trigger)
+    }
+
+    pub fn is_shown(&self) -> bool {
+        !self.queue.is_empty()
+    }
+
+    /// Closes the current popup and moves on to the next one.
+    pub fn close_current(&mut self, events: &mut Vec<Event>) -> Option<DialogAction> {
+        let (_, step) = self.queue.pop_front()?;
+        self.play_current_sound(events);
+        step.on_close
+    }
+
+    fn play_current_sound(&self, events: &mut Vec<Event>) {
+        if let Some(sfx) = self.current().and_then(|step| step.sound) {
+            events.push(Event::Sound(sfx));
+        }
+    }
+}
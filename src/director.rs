@@ -0,0 +1,404 @@
+// Decides what spawns when. A wave script (`assets/waves.ron`) sets the
+// pace of the run, and the density is scaled by the difficulty, the time
+// survived and how badly the player is doing.
+
+use std::f32::consts::TAU;
+
+use macroquad::math::Vec2;
+use nanoserde::DeRon;
+
+use crate::{
+    archetypes::Archetypes,
+    entities::{Entity, CENTER, SPAWN_DIST},
+    rng::{fnv1a, Rng},
+    timestep::TICK_DT,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+const WAVES_PATH: &str = "assets/waves.ron";
+/// Gap between the enemies of a `Formation::Line`
+const LINE_SPACING: f32 = 1.5;
+/// Kept every tick from the hits counted in `Director::recent_hits`
+const HIT_DECAY: f32 = 0.995;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+/// How a difficulty scales the wave script.
+struct Preset {
+    /// Spawn rate multiplier at the start of a run
+    density: f32,
+    /// Added to the multiplier every minute
+    ramp: f32,
+    /// How much low hp and recent hits slow spawns down
+    mercy: f32,
+}
+
+impl Difficulty {
+    pub const ALL: [Self; 3] = [Self::Easy, Self::Normal, Self::Hard];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Easy => "Easy",
+            Self::Normal => "Normal",
+            Self::Hard => "Hard",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|d| d.name() == name)
+    }
+
+    const fn preset(self) -> Preset {
+        match self {
+            Self::Easy => Preset {
+                density: 0.7,
+                ramp: 0.15,
+                mercy: 1.5,
+            },
+            Self::Normal => Preset {
+                density: 1.,
+                ramp: 0.25,
+                mercy: 1.,
+            },
+            Self::Hard => Preset {
+                density: 1.3,
+                ramp: 0.4,
+                mercy: 0.3,
+            },
+        }
+    }
+}
+
+/// Where the enemies of one spawn show up on the spawn ring.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, DeRon)]
+pub enum Formation {
+    /// One enemy anywhere
+    Single,
+    /// That many enemies, each anywhere
+    Burst(u32),
+    /// That many enemies evenly around the arena
+    Ring(u32),
+    /// That many enemies side by side, coming from the same direction
+    Line(u32),
+}
+
+impl Formation {
+    fn positions(self, rng: &mut Rng) -> Vec<Vec2> {
+        let on_ring = |angle: f32| CENTER + Vec2::from_angle(angle) * SPAWN_DIST;
+        match self {
+            Self::Single => vec![on_ring(rng.gen_range(0., TAU))],
+            Self::Burst(count) => (0..count)
+                .map(|_| on_ring(rng.gen_range(0., TAU)))
+                .collect(),
+            Self::Ring(count) => {
+                let start = rng.gen_range(0., TAU);
+                (0..count)
+                    .map(|i| on_ring(start + TAU * i as f32 / count as f32))
+                    .collect()
+            }
+            Self::Line(count) => {
+                let angle = rng.gen_range(0., TAU);
+                let side = Vec2::from_angle(angle).perp();
+                (0..count)
+                    .map(|i| {
+                        let offset = i as f32 - (count as f32 - 1.) / 2.;
+                        on_ring(angle) + side * offset * LINE_SPACING
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, DeRon)]
+pub struct WaveSpawn {
+    /// `Archetype::id`
+    pub enemy: String,
+    /// Seconds between spawns at a density of 1
+    pub every: f32,
+    #[nserde(default)]
+    pub formation: Option<Formation>,
+    /// Index in `Archetypes::archetypes`
+    #[nserde(skip)]
+    pub archetype: usize,
+}
+
+#[derive(Clone, Debug, DeRon)]
+pub struct Wave {
+    pub name: String,
+    /// In seconds
+    pub duration: f32,
+    #[nserde(default)]
+    pub spawns: Vec<WaveSpawn>,
+    /// Multiplier of the archetypes' own `spawn_every` cadence, 0 for a rest
+    #[nserde(default)]
+    pub ambient: Option<f32>,
+}
+
+#[derive(Clone, Debug, DeRon)]
+pub struct WaveScript {
+    pub waves: Vec<Wave>,
+    /// Wave to go back to after the last one
+    #[nserde(default)]
+    pub loop_from: usize,
+    /// Of the file it was read from, replays only play with the same
+    #[nserde(skip)]
+    pub hash: u64,
+}
+
+impl WaveScript {
+    /// Script from `assets/waves.ron`. Desktop builds read the file from the
+    /// working directory first, like `Archetypes::load`, and keep the
+    /// built-in script if it's missing or invalid.
+    pub fn load(archetypes: &Archetypes) -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Ok(ron) = std::fs::read_to_string(WAVES_PATH) {
+            match Self::from_ron(&ron, archetypes) {
+                Ok(script) => return script,
+                Err(err) => macroquad::logging::warn!(
+                    "Ignoring {}, using the built-in waves: {}",
+                    WAVES_PATH,
+                    err
+                ),
+            }
+        }
+
+        Self::from_ron(include_str!("../assets/waves.ron"), archetypes)
+            .unwrap_or_else(|err| panic!("assets/waves.ron: {err}"))
+    }
+
+    pub fn from_ron(ron: &str, archetypes: &Archetypes) -> Result<Self, String> {
+        let mut script = Self::deserialize_ron(ron).map_err(|e| e.to_string())?;
+        script.hash = fnv1a(ron.as_bytes());
+
+        if script.waves.is_empty() {
+            return Err("no waves".to_owned());
+        }
+        if script.loop_from >= script.waves.len() {
+            return Err(format!(
+                "loop_from is {} but there are only {} waves",
+                script.loop_from,
+                script.waves.len()
+            ));
+        }
+
+        for (i, wave) in script.waves.iter_mut().enumerate() {
+            let name = format!("wave #{i} \"{}\"", wave.name);
+            if !wave.duration.is_finite() || wave.duration <= 0. {
+                return Err(format!("{name}: duration must be positive"));
+            }
+            if wave.ambient.is_some_and(|a| !a.is_finite() || a < 0.) {
+                return Err(format!("{name}: ambient can't be negative"));
+            }
+            for spawn in &mut wave.spawns {
+                spawn.archetype = archetypes
+                    .archetypes
+                    .iter()
+                    .position(|a| a.id == spawn.enemy)
+                    .ok_or_else(|| format!("{name}: unknown enemy \"{}\"", spawn.enemy))?;
+                if !spawn.every.is_finite() || spawn.every <= 0. {
+                    return Err(format!(
+                        "{name}: \"{}\" needs a positive every",
+                        spawn.enemy
+                    ));
+                }
+            }
+        }
+
+        Ok(script)
+    }
+}
+
+pub struct Director {
+    pub difficulty: Difficulty,
+    pub script: WaveScript,
+    /// Index in `script.waves`
+    pub wave: usize,
+    /// Seconds since the current wave started
+    pub wave_time: f32,
+    /// Times the script went back to `loop_from`
+    pub loops: u32,
+    /// Seconds toward the next spawn of each `WaveSpawn` of the wave
+    pub progress: Vec<f32>,
+    /// Ticks toward the next spawn of each archetype's own cadence
+    pub ambient: Vec<f32>,
+    /// Player hits, fading out over a few seconds
+    pub recent_hits: f32,
+    /// Spawn rate multiplier of the last tick
+    pub density: f32,
+    /// Factor of `density` that eases off while the player is struggling, 1
+    /// when they aren't
+    pub mercy: f32,
+}
+
+impl Director {
+    /// `script` must have been checked against `archetypes`.
+    pub fn new(archetypes: &Archetypes, script: WaveScript) -> Self {
+        Self {
+            difficulty: Difficulty::default(),
+            progress: vec![0.; script.waves[0].spawns.len()],
+            script,
+            wave: 0,
+            wave_time: 0.,
+            loops: 0,
+            ambient: vec![0.; archetypes.len()],
+            recent_hits: 0.,
+            density: 0.,
+            mercy: 1.,
+        }
+    }
+
+    /// Back to the first wave, keeping the difficulty.
+    pub fn reset(&mut self) {
+        self.start_wave(0);
+        self.loops = 0;
+        self.ambient.fill(0.);
+        self.recent_hits = 0.;
+        self.density = 0.;
+        self.mercy = 1.;
+    }
+
+    pub fn current_wave(&self) -> &Wave {
+        &self.script.waves[self.wave]
+    }
+
+    fn start_wave(&mut self, wave: usize) {
+        self.wave = wave;
+        self.wave_time = 0.;
+        self.progress.clear();
+        self.progress
+            .resize(self.script.waves[wave].spawns.len(), 0.);
+    }
+
+    pub fn player_hit(&mut self) {
+        self.recent_hits += 1.;
+    }
+
+    /// Pushes this tick's spawns into `enemies`. `timer` is the time
+    /// survived, in seconds.
+    pub fn tick(
+        &mut self,
+        archetypes: &Archetypes,
+        timer: f32,
+        hp: u8,
+        target_pos: Vec2,
+        rng: &mut Rng,
+        enemies: &mut Vec<Entity>,
+    ) {
+        let preset = self.difficulty.preset();
+        self.recent_hits *= HIT_DECAY;
+        let struggle = f32::from(3 - hp.min(3)) / 3. + self.recent_hits * 0.25;
+        self.mercy = 1. / (1. + preset.mercy * struggle);
+        self.density = (preset.density + preset.ramp * timer / 60.) * self.mercy;
+
+        self.wave_time += TICK_DT;
+        if self.wave_time >= self.current_wave().duration {
+            let next = self.wave + 1;
+            if next < self.script.waves.len() {
+                self.start_wave(next);
+            } else {
+                self.loops += 1;
+                self.start_wave(self.script.loop_from);
+            }
+        }
+
+        let wave = &self.script.waves[self.wave];
+        for (spawn, progress) in wave.spawns.iter().zip(&mut self.progress) {
+            *progress += TICK_DT * self.density;
+            if *progress >= spawn.every {
+                *progress -= spawn.every;
+                let stats = archetypes.stats(spawn.archetype);
+                for pos in spawn.formation.unwrap_or(Formation::Single).positions(rng) {
+                    enemies.push(Entity::spawn(stats, pos, target_pos, rng));
+                }
+            }
+        }
+
+        let ambient = wave.ambient.unwrap_or(1.);
+        for (i, archetype) in archetypes.archetypes.iter().enumerate() {
+            let Some(every) = archetype.spawn_every else {
+                continue;
+            };
+            self.ambient[i] += self.density * ambient;
+            if self.ambient[i] >= every as f32 {
+                self.ambient[i] -= every as f32;
+                let pos = Formation::Single.positions(rng)[0];
+                enemies.push(Entity::spawn(archetypes.stats(i), pos, target_pos, rng));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{archetypes, script};
+
+    fn error(ron: &str) -> String {
+        WaveScript::from_ron(ron, &archetypes())
+            .err()
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn shipped_script_is_valid() {
+        let archetypes = archetypes();
+        let script = script(&archetypes);
+
+        for spawn in script.waves.iter().flat_map(|w| &w.spawns) {
+            assert_eq!(archetypes.archetypes[spawn.archetype].id, spawn.enemy);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_scripts() {
+        for (ron, expected) in [
+            ("(waves: [])", "no waves"),
+            (
+                "(loop_from: 1, waves: [(name: \"a\", duration: 1.)])",
+                "loop_from",
+            ),
+            ("(waves: [(name: \"a\", duration: 0.)])", "duration"),
+            (
+                "(waves: [(name: \"a\", duration: 1., ambient: -1.)])",
+                "ambient",
+            ),
+            (
+                "(waves: [(name: \"a\", duration: 1., spawns: [(enemy: \"boss\", every: 1.)])])",
+                "unknown enemy \"boss\"",
+            ),
+            (
+                "(waves: [(name: \"a\", duration: 1., spawns: [(enemy: \"bullet\", every: 0.)])])",
+                "positive every",
+            ),
+        ] {
+            let err = error(ron);
+            assert!(err.contains(expected), "{ron}: {err}");
+        }
+    }
+
+    #[test]
+    fn loops_back_after_the_last_wave() {
+        let archetypes = archetypes();
+        let ron = "(loop_from: 1, waves: [(name: \"a\", duration: 1., ambient: 0.), \
+                   (name: \"b\", duration: 1., ambient: 0.)])";
+        let mut director =
+            Director::new(&archetypes, WaveScript::from_ron(ron, &archetypes).unwrap());
+        let mut rng = Rng::new(1);
+        let mut enemies = Vec::new();
+
+        let ticks = (3. / TICK_DT).round() as u32;
+        for _ in 0..ticks {
+            director.tick(&archetypes, 0., 3, CENTER, &mut rng, &mut enemies);
+        }
+
+        assert_eq!((director.wave, director.loops), (1, 1));
+        assert!(enemies.is_empty());
+    }
+}
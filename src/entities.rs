@@ -1,18 +1,44 @@
-use std::{
-    collections::VecDeque,
-    f32::consts::{PI, TAU},
-};
+use std::f32::consts::{PI, TAU};
 
 use macroquad::prelude::*;
 
+use crate::{
+    abilities::SLOW_RANGE,
+    archetypes::{Behavior, Stats},
+    events::ItemKind,
+    paths::{PathFamily, SplinePath},
+    rng::Rng,
+    steering::{Body, SteeringParams},
+};
+
 #[derive(Clone)]
 pub enum EntityType {
-    Bullet,                 // Red circle
-    Follower,               // Yellow triangle
-    Pather(VecDeque<Vec2>), // Green square
-    Player,                 // The player
-    HealItem,               // Hearth that heals the player
-    ManaItem,               // blue circle that give mana to player
+    Bullet, // Red circle
+    // Yellow triangle, `force` is set by `World` every tick
+    Follower {
+        steering: SteeringParams,
+        force: Vec2,
+    },
+    // Green square, `travelled` is its distance along `path`
+    Pather {
+        path: SplinePath,
+        travelled: f32,
+        pace: f32,
+    },
+    Splitter(u32), // Cyan hexagon, ticks left before it bursts
+    // Orange ring circling the player closer and closer, shooting at it
+    Orbiter {
+        angle: f32,
+        orbit: f32,
+        reload: u32,
+        max_speed: f32,
+    },
+    Player, // The player
+    // Picked when touched, gone when `lifetime` (ticks) runs out
+    Item {
+        kind: ItemKind,
+        lifetime: u32,
+    },
 }
 
 #[derive(Clone)]
@@ -24,26 +50,40 @@ pub struct Entity {
     pub alive: bool,
     pub rotation: f32,
     pub is_clone: bool,
+    /// Reflected bullet, only hurts enemies
+    pub friendly: bool,
     pub hit_anim: u32,
+    /// Position at the start of the last tick, to interpolate drawing
+    pub prev_pos: Vec2,
+    /// Index in `World::archetypes`, only meaningful for enemies
+    pub archetype: usize,
+    /// Power hits left before being destroyed
+    pub hp: u32,
 }
 
-const SPAWN_DIST: f32 = 30.;
-const BULLET_SPEED: f32 = 0.25;
-const FOLLOWER_ACCELERATION: f32 = 0.01;
-const PATHER_SPEED: f32 = 0.25;
+pub const SPAWN_DIST: f32 = 30.;
+const SPLITTER_LIFETIME: u32 = 300;
+const SPLITTER_SHARDS: u32 = 8;
+const ORBITER_START_ORBIT: f32 = 12.;
+const ORBITER_SHRINK: f32 = 0.01;
+const ORBITER_ANGULAR_SPEED: f32 = 0.02;
+const ORBITER_RELOAD: u32 = 90;
+const ITEM_LIFETIME: u32 = 600;
+/// Items blink for that many ticks before they despawn
+pub const ITEM_BLINK: u32 = 120;
 pub const WORLD_WIDTH: f32 = 40.;
 pub const WORLD_HEIGHT: f32 = 30.;
 pub const CENTER: Vec2 = Vec2::new(WORLD_WIDTH / 2., WORLD_HEIGHT / 2.);
 
-fn random_outside_pos() -> Vec2 {
-    let angle = rand::gen_range(0., TAU);
+pub fn random_outside_pos(rng: &mut Rng) -> Vec2 {
+    let angle = rng.gen_range(0., TAU);
 
     Vec2::from_angle(angle) * SPAWN_DIST + CENTER
 }
 
-fn random_inside_pos() -> Vec2 {
-    let x: f32 = rand::gen_range(0., WORLD_WIDTH);
-    let y: f32 = rand::gen_range(0., WORLD_HEIGHT);
+pub fn random_inside_pos(rng: &mut Rng) -> Vec2 {
+    let x: f32 = rng.gen_range(0., WORLD_WIDTH);
+    let y: f32 = rng.gen_range(0., WORLD_HEIGHT);
 
     Vec2::new(x, y)
 }
@@ -58,106 +98,263 @@ impl Entity {
             alive: true,
             rotation: PI / 2.,
             is_clone: false,
+            friendly: false,
             hit_anim: 0,
+            prev_pos: CENTER,
+            archetype: 0,
+            hp: 1,
+        }
+    }
+
+    /// A new enemy of that archetype at `pos`, usually on the spawn ring,
+    /// going after `target_pos`.
+    pub fn spawn(stats: Stats, pos: Vec2, target_pos: Vec2, rng: &mut Rng) -> Self {
+        match stats.behavior {
+            Behavior::Bullet => Self::new_aimed_bullet(stats, pos, target_pos),
+            Behavior::Follower => Self::new_random_follower(stats, pos, target_pos, rng),
+            Behavior::Pather => Self::new_random_pather(stats, pos, target_pos, rng),
+            Behavior::Splitter => Self::new_splitter(stats, pos, target_pos),
+            Behavior::Orbiter => Self::new_orbiter(stats, pos, target_pos),
         }
     }
 
-    pub fn new_random_bullet(target_pos: Vec2) -> Self {
-        let pos = random_outside_pos();
-        let speed = (target_pos - pos).normalize() * BULLET_SPEED;
+    fn new_aimed_bullet(stats: Stats, pos: Vec2, target_pos: Vec2) -> Self {
+        let speed = (target_pos - pos).normalize() * stats.speed;
+
+        Self::new_bullet(stats, pos, speed)
+    }
 
+    pub const fn new_bullet(stats: Stats, pos: Vec2, speed: Vec2) -> Self {
         Self {
             pos,
             speed,
             e_type: EntityType::Bullet,
-            radius: 0.25,
+            radius: stats.radius,
             alive: true,
             rotation: 0.,
             is_clone: false,
+            friendly: false,
             hit_anim: 0,
+            prev_pos: pos,
+            archetype: stats.archetype,
+            hp: stats.hp,
         }
     }
 
-    pub fn new_random_follower(target_pos: Vec2) -> Self {
-        let pos = random_outside_pos();
-        let speed = (target_pos - pos).normalize() * BULLET_SPEED;
+    /// `count` bullets flying away from `pos`, the first one at `angle`.
+    pub fn bullet_ring(
+        stats: Stats,
+        pos: Vec2,
+        count: u32,
+        angle: f32,
+    ) -> impl Iterator<Item = Self> {
+        (0..count).map(move |i| {
+            let angle = angle + TAU * i as f32 / count as f32;
+            Self::new_bullet(stats, pos, Vec2::from_angle(angle) * stats.speed)
+        })
+    }
+
+    fn new_random_follower(stats: Stats, pos: Vec2, target_pos: Vec2, rng: &mut Rng) -> Self {
+        let speed = (target_pos - pos).normalize() * stats.speed;
+        let side = *rng.choose(&[-1., 1.]).unwrap();
+        let steering = stats.steering.expect("checked by Archetypes::from_ron");
 
+        Self::new_follower(
+            stats,
+            pos,
+            speed,
+            SteeringParams {
+                flank: steering.flank * side,
+                ..steering
+            },
+        )
+    }
+
+    /// Steers toward the player with `steering`, see `World::steer_followers`.
+    pub fn new_follower(stats: Stats, pos: Vec2, speed: Vec2, steering: SteeringParams) -> Self {
         Self {
             pos,
             speed,
-            e_type: EntityType::Follower,
-            radius: 0.5,
+            e_type: EntityType::Follower {
+                steering,
+                force: Vec2::ZERO,
+            },
+            radius: stats.radius,
             alive: true,
             rotation: speed.y.atan2(speed.x),
             is_clone: false,
+            friendly: false,
             hit_anim: 0,
+            prev_pos: pos,
+            archetype: stats.archetype,
+            hp: stats.hp,
         }
     }
 
-    pub fn new_random_pather() -> Self {
-        let pos = random_outside_pos();
-        let speed = Vec2::new(PATHER_SPEED, PATHER_SPEED);
-        let mut path = VecDeque::new();
-        path.push_back(random_outside_pos());
-        for _ in 0..3 {
-            path.push_back(random_inside_pos());
+    fn new_random_pather(stats: Stats, pos: Vec2, target_pos: Vec2, rng: &mut Rng) -> Self {
+        let family = *rng.choose(&PathFamily::ALL).unwrap();
+
+        Self::new_pather(stats, family.path(pos, target_pos, rng))
+    }
+
+    /// Follows `path` at constant speed, then disappears.
+    pub fn new_pather(stats: Stats, path: SplinePath) -> Self {
+        let pos = path.start();
+        Self {
+            pos,
+            speed: Vec2::ZERO,
+            e_type: EntityType::Pather {
+                path,
+                travelled: 0.,
+                pace: stats.speed,
+            },
+            radius: stats.radius,
+            alive: true,
+            rotation: 0.,
+            is_clone: false,
+            friendly: false,
+            hit_anim: 0,
+            prev_pos: pos,
+            archetype: stats.archetype,
+            hp: stats.hp,
         }
-        path.push_back(random_outside_pos());
+    }
+
+    fn new_splitter(stats: Stats, pos: Vec2, target_pos: Vec2) -> Self {
+        let speed = (target_pos - pos).normalize() * stats.speed;
 
         Self {
             pos,
             speed,
-            e_type: EntityType::Pather(path),
-            radius: 0.25,
+            e_type: EntityType::Splitter(SPLITTER_LIFETIME),
+            radius: stats.radius,
             alive: true,
             rotation: 0.,
             is_clone: false,
+            friendly: false,
             hit_anim: 0,
+            prev_pos: pos,
+            archetype: stats.archetype,
+            hp: stats.hp,
         }
     }
 
-    pub fn new_heal_item() -> Self {
-        let pos: Vec2 = random_inside_pos();
+    /// The ring of `bullet`s a splitter breaks into, aimed outward.
+    pub fn splitter_shards(&self, bullet: Stats) -> impl Iterator<Item = Self> {
+        let is_clone = self.is_clone;
+        Self::bullet_ring(bullet, self.pos, SPLITTER_SHARDS, self.rotation)
+            .map(move |shard| Self { is_clone, ..shard })
+    }
+
+    /// Shot down by the player, whatever the weapon. Unlike crashing into the
+    /// player, it makes splitters burst.
+    pub const fn destroy(&mut self) {
+        self.alive = false;
+        if let EntityType::Splitter(lifetime) = &mut self.e_type {
+            *lifetime = 0;
+        }
+    }
+
+    /// Splitters burst when destroyed or when their time runs out, but not
+    /// when they hit the player.
+    pub const fn bursts(&self) -> bool {
+        !self.alive && matches!(self.e_type, EntityType::Splitter(0))
+    }
+
+    fn new_orbiter(stats: Stats, pos: Vec2, target_pos: Vec2) -> Self {
+        let offset = pos - target_pos;
 
         Self {
             pos,
             speed: Vec2::ZERO,
-            e_type: EntityType::HealItem,
-            radius: 0.5,
+            e_type: EntityType::Orbiter {
+                angle: offset.y.atan2(offset.x),
+                orbit: ORBITER_START_ORBIT,
+                reload: ORBITER_RELOAD,
+                max_speed: stats.speed,
+            },
+            radius: stats.radius,
             alive: true,
+            rotation: 0.,
             is_clone: false,
-            rotation: rand::gen_range(0., TAU),
+            friendly: false,
             hit_anim: 0,
+            prev_pos: pos,
+            archetype: stats.archetype,
+            hp: stats.hp,
         }
     }
 
-    pub fn new_mana_item() -> Self {
-        let pos: Vec2 = random_inside_pos();
+    /// A `bullet` aimed at `target_pos` if the entity is a shooter and just
+    /// reloaded.
+    pub fn fire(&mut self, target_pos: Vec2, bullet: Stats) -> Option<Self> {
+        let EntityType::Orbiter { reload, .. } = &mut self.e_type else {
+            return None;
+        };
+        if *reload > 0 {
+            return None;
+        }
+        *reload = ORBITER_RELOAD;
+
+        let speed = (target_pos - self.pos).normalize_or_zero() * bullet.speed;
+        Some(Self {
+            is_clone: self.is_clone,
+            ..Self::new_bullet(bullet, self.pos, speed)
+        })
+    }
+
+    pub fn new_item(kind: ItemKind, rng: &mut Rng) -> Self {
+        let pos: Vec2 = random_inside_pos(rng);
 
         Self {
             pos,
             speed: Vec2::ZERO,
-            e_type: EntityType::ManaItem,
+            e_type: EntityType::Item {
+                kind,
+                lifetime: ITEM_LIFETIME,
+            },
             radius: 0.5,
             alive: true,
             is_clone: false,
-            rotation: rand::gen_range(0., TAU),
+            friendly: false,
+            rotation: rng.gen_range(0., TAU),
             hit_anim: 0,
+            prev_pos: pos,
+            archetype: 0,
+            hp: 1,
+        }
+    }
+
+    pub const fn body(&self) -> Body {
+        Body {
+            pos: self.pos,
+            speed: self.speed,
         }
     }
 
-    pub fn tick(&mut self, target_pos: Vec2) {
+    /// Where to draw the entity, `alpha` of the way through the next tick.
+    pub fn draw_pos(&self, alpha: f32) -> Vec2 {
+        self.prev_pos.lerp(self.pos, alpha)
+    }
+
+    /// Entities within `SLOW_RANGE` of `slow_field`, if any, stand still
+    /// this tick.
+    pub fn tick(&mut self, target_pos: Vec2, slow_field: Option<Vec2>) {
         if self.hit_anim > 0 {
             self.hit_anim -= 1;
         }
+        if slow_field.is_some_and(|center| (self.pos - center).length() < SLOW_RANGE) {
+            return;
+        }
 
         match &mut self.e_type {
             EntityType::Bullet => self.bullet_tick(),
-            EntityType::Follower => self.follower_tick(target_pos),
-            EntityType::Pather(_) => self.pather_tick(),
+            EntityType::Follower { .. } => self.follower_tick(),
+            EntityType::Pather { .. } => self.pather_tick(),
+            EntityType::Splitter(_) => self.splitter_tick(),
+            EntityType::Orbiter { .. } => self.orbiter_tick(target_pos),
             EntityType::Player => self.player_tick(),
-            EntityType::HealItem => (),
-            EntityType::ManaItem => (),
+            EntityType::Item { .. } => self.item_tick(),
         }
     }
 
@@ -177,6 +374,20 @@ impl Entity {
         }
     }
 
+    /// Items only move while pulled by a magnet, `World` sets their speed.
+    fn item_tick(&mut self) {
+        let EntityType::Item { lifetime, .. } = &mut self.e_type else {
+            unreachable!()
+        };
+
+        if let Some(left) = lifetime.checked_sub(1) {
+            *lifetime = left;
+        } else {
+            self.alive = false;
+        }
+        self.pos += self.speed;
+    }
+
     fn bullet_tick(&mut self) {
         if (self.pos - CENTER).length() > SPAWN_DIST + 1. {
             self.alive = false;
@@ -184,9 +395,12 @@ impl Entity {
         self.pos += self.speed;
     }
 
-    fn follower_tick(&mut self, target_pos: Vec2) {
-        self.speed += (target_pos - self.pos).normalize() * FOLLOWER_ACCELERATION;
-        self.speed *= 0.95;
+    fn follower_tick(&mut self) {
+        let EntityType::Follower { steering, force } = &self.e_type else {
+            unreachable!()
+        };
+
+        self.speed = (self.speed + *force).clamp_length_max(steering.max_speed);
         self.rotation = self.speed.y.atan2(self.speed.x);
         self.radius -= 0.001;
         if self.radius < 0.1 {
@@ -195,23 +409,64 @@ impl Entity {
         self.pos += self.speed;
     }
 
+    fn splitter_tick(&mut self) {
+        self.rotation += 0.02;
+        let EntityType::Splitter(lifetime) = &mut self.e_type else {
+            unreachable!()
+        };
+
+        if let Some(left) = lifetime.checked_sub(1) {
+            *lifetime = left;
+        } else {
+            self.alive = false;
+        }
+        self.pos += self.speed;
+    }
+
+    fn orbiter_tick(&mut self, target_pos: Vec2) {
+        let EntityType::Orbiter {
+            angle,
+            orbit,
+            reload,
+            max_speed,
+        } = &mut self.e_type
+        else {
+            unreachable!()
+        };
+
+        *angle += ORBITER_ANGULAR_SPEED;
+        *orbit = (*orbit - ORBITER_SHRINK).max(0.);
+        *reload = reload.saturating_sub(1);
+
+        let slot = target_pos + Vec2::from_angle(*angle) * *orbit;
+        self.speed = (slot - self.pos).clamp_length_max(*max_speed);
+        self.rotation = *angle;
+        self.pos += self.speed;
+    }
+
     fn pather_tick(&mut self) {
         self.rotation += 0.04;
-        let path = match &mut self.e_type {
-            EntityType::Pather(path) => path,
-            _ => {
-                unreachable!()
-            }
+        if let EntityType::Pather { pace, .. } = self.e_type {
+            self.follow_path(pace);
+        }
+    }
+
+    /// Moves a pather `distance` further along its path. It moves by as much
+    /// as the path does, so a clone shifted aside follows a parallel path.
+    pub fn follow_path(&mut self, distance: f32) {
+        let EntityType::Pather {
+            path, travelled, ..
+        } = &mut self.e_type
+        else {
+            return;
         };
 
-        if let Some(point) = path.front() {
-            self.speed = (*point - self.pos).normalize() * PATHER_SPEED;
-            if (self.pos - *point).length() < self.radius + 1. {
-                path.pop_front().unwrap();
-            }
-        } else {
+        if *travelled >= path.length() {
             self.alive = false;
         }
+        let next = *travelled + distance;
+        self.speed = path.point_at(next) - path.point_at(*travelled);
+        *travelled = next;
         self.pos += self.speed;
     }
 }
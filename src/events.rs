@@ -0,0 +1,122 @@
+use macroquad::math::Vec2;
+use nanoserde::DeRon;
+
+/// Sound effects the frontend can play. `Resources` maps them to the loaded
+/// `Sound`s.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, DeRon)]
+pub enum Sfx {
+    Hit,
+    PickItem,
+    Explosion,
+    Glitch,
+    SmallBug,
+    OrDidYou,
+    ExplosionBug,
+    /// Each pickup has its own sound
+    Pickup(ItemKind),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, DeRon)]
+pub enum ItemKind {
+    Heal,
+    Mana,
+    /// Destroyed enemies are worth more for a while
+    Score,
+    /// The player moves faster for a while
+    Speed,
+    /// Lowers the instability
+    Patch,
+    /// Items fly to the player for a while
+    Magnet,
+    /// Same as the shield ability
+    Shield,
+}
+
+impl ItemKind {
+    pub const ALL: [Self; 7] = [
+        Self::Heal,
+        Self::Mana,
+        Self::Score,
+        Self::Speed,
+        Self::Patch,
+        Self::Magnet,
+        Self::Shield,
+    ];
+}
+
+/// What happened during a tick, in order.
+///
+/// The simulation only reports gameplay facts; `World`'s bug rules, the
+/// audio, the glitch screen effect and the BSOD screen each subscribe to the
+/// ones they care about.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Event {
+    /// Touched by an enemy, even while still blinking from the last hit
+    PlayerHit,
+    /// Hit with no hp left
+    HpUnderflow,
+    /// Healed with full hp
+    HpOverflow,
+    /// Picked mana with full mana
+    ManaOverflow,
+    /// Used the power without enough mana
+    ManaUnderflow,
+    ItemPicked(ItemKind),
+    /// `Ability::name`, with the sound it makes
+    AbilityUsed {
+        name: &'static str,
+        sfx: Sfx,
+    },
+    /// Shot down, counts for the score
+    EnemyDestroyed {
+        pos: Vec2,
+    },
+    /// Crashed into the player, doesn't count for the score
+    EnemyCollided {
+        pos: Vec2,
+    },
+    /// Went through the top of the screen
+    LeftArena,
+    /// The world is unstable and rolled for something to glitch
+    Unstable {
+        instability: u32,
+    },
+    /// `Glitch::name`
+    GlitchStarted(&'static str),
+    /// A glitched copy of an enemy appeared
+    GlitchSpawned {
+        pos: Vec2,
+    },
+    KernelSpawned,
+    /// Weak point hit by the power, `hp` left
+    KernelHit {
+        hp: u32,
+    },
+    KernelDefeated {
+        pos: Vec2,
+    },
+
+    InstabilityRaised,
+    /// Achievement id
+    AchievementUnlocked(String),
+    Bsod(String),
+    /// Played as is, for sounds that aren't tied to gameplay (dialogs)
+    Sound(Sfx),
+}
+
+impl Event {
+    /// The audio subscriber.
+    pub const fn sfx(&self) -> Option<Sfx> {
+        Some(match self {
+            Self::PlayerHit => Sfx::Hit,
+            Self::ItemPicked(kind) => Sfx::Pickup(*kind),
+            Self::AbilityUsed { sfx, .. } | Self::Sound(sfx) => *sfx,
+            Self::GlitchStarted(_) | Self::GlitchSpawned { .. } | Self::KernelSpawned => {
+                Sfx::Glitch
+            }
+            Self::KernelDefeated { .. } => Sfx::ExplosionBug,
+            Self::KernelHit { .. } | Self::InstabilityRaised => Sfx::SmallBug,
+            _ => return None,
+        })
+    }
+}
@@ -0,0 +1,371 @@
+use crate::{
+    entities::{self, Entity, EntityType, WORLD_HEIGHT, WORLD_WIDTH},
+    events::Event,
+    world::World,
+};
+
+const GLITCH_SPEED: u32 = 10;
+const SURGE_FACTOR: f32 = 2.;
+
+/// Chance in percent, per tick, that a glitch starts at instability 1 to 5.
+const GLITCH_CHANCE: [f32; 5] = [0.1, 0.5, 0.7, 1., 10.];
+
+/// Lasting effects of the running glitch, read by the simulation (controls)
+/// and by the frontend (sprites, HUD, frozen display).
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct GlitchFlags {
+    pub inverted_controls: bool,
+    /// How far enemy sprites are rotated between the archetypes
+    pub sprite_shift: usize,
+    pub hud_corrupted: bool,
+    /// The display stops updating while the world keeps going
+    pub frozen: bool,
+}
+
+/// One kind of glitch. Only one runs at a time, picked at random among the
+/// registered ones according to their `weight` for the current instability.
+pub trait Glitch {
+    fn name(&self) -> &'static str;
+
+    /// Relative chance to be picked, 0 to never happen at that instability.
+    fn weight(&self, instability: u32) -> f32;
+
+    /// In ticks.
+    fn duration(&self, instability: u32) -> u32;
+
+    /// Returns `false` if the glitch can't happen right now, e.g. there is
+    /// nothing to duplicate.
+    fn start(&mut self, _world: &mut World, _events: &mut Vec<Event>) -> bool {
+        true
+    }
+
+    /// Called every tick while running, `elapsed` starts at 0.
+    fn tick(&mut self, _world: &mut World, _elapsed: u32, _events: &mut Vec<Event>) {}
+
+    fn stop(&mut self, _world: &mut World) {}
+}
+
+/// Weight or duration for an instability level from a table indexed by
+/// level - 1.
+fn per_level<T: Copy + Default>(table: [T; 5], instability: u32) -> T {
+    instability
+        .checked_sub(1)
+        .and_then(|i| table.get(i as usize).copied())
+        .unwrap_or_default()
+}
+
+#[derive(Default)]
+pub struct Glitches {
+    registry: Vec<Box<dyn Glitch>>,
+    /// Index in `registry`, ticks elapsed
+    active: Option<(usize, u32)>,
+}
+
+impl Glitches {
+    /// All the built-in glitches.
+    pub fn new() -> Self {
+        let mut glitches = Self::default();
+        glitches.register(Box::<Duplication>::default());
+        glitches.register(Box::new(InvertedControls));
+        glitches.register(Box::new(Teleport));
+        glitches.register(Box::new(SpeedSurge));
+        glitches.register(Box::new(SpriteSwap));
+        glitches.register(Box::new(HudCorruption));
+        glitches.register(Box::new(FrozenFrames));
+        glitches
+    }
+
+    pub fn register(&mut self, glitch: Box<dyn Glitch>) {
+        self.registry.push(glitch);
+    }
+
+    /// Name of the running glitch.
+    pub fn active(&self) -> Option<&'static str> {
+        self.active.map(|(i, _)| self.registry[i].name())
+    }
+
+    /// Name, ticks elapsed and duration of the running glitch.
+    pub fn progress(&self, instability: u32) -> Option<(&'static str, u32, u32)> {
+        self.active.map(|(i, elapsed)| {
+            let glitch = &self.registry[i];
+            (glitch.name(), elapsed, glitch.duration(instability))
+        })
+    }
+
+    /// Drops the running glitch without calling `Glitch::stop`, for when the
+    /// whole world is reset anyway.
+    pub const fn cancel(&mut self) {
+        self.active = None;
+    }
+
+    /// Runs the active glitch, or rolls for a new one.
+    pub fn tick(&mut self, world: &mut World, events: &mut Vec<Event>) {
+        if let Some((index, elapsed)) = self.active {
+            let glitch = &mut self.registry[index];
+            if elapsed < glitch.duration(world.instability) {
+                glitch.tick(world, elapsed, events);
+                self.active = Some((index, elapsed + 1));
+            } else {
+                glitch.stop(world);
+                self.active = None;
+            }
+            return;
+        }
+
+        let chance = per_level(GLITCH_CHANCE, world.instability);
+        if chance == 0. {
+            return;
+        }
+        events.push(Event::Unstable {
+            instability: world.instability,
+        });
+        if world.rng.gen_range(0., 100.) >= chance {
+            return;
+        }
+
+        let total: f32 = self
+            .registry
+            .iter()
+            .map(|g| g.weight(world.instability))
+            .sum();
+        if total <= 0. {
+            return;
+        }
+        let mut roll = world.rng.gen_range(0., total);
+        let Some(index) = self.registry.iter().position(|g| {
+            roll -= g.weight(world.instability);
+            roll < 0.
+        }) else {
+            return;
+        };
+
+        if self.registry[index].start(world, events) {
+            events.push(Event::GlitchStarted(self.registry[index].name()));
+            self.active = Some((index, 0));
+        }
+    }
+}
+
+/// Clones an enemy and spawns copies of it diagonally.
+#[derive(Default)]
+pub struct Duplication {
+    original: Option<Entity>,
+    x_direction: i32,
+    y_direction: i32,
+}
+
+impl Glitch for Duplication {
+    fn name(&self) -> &'static str {
+        "duplication"
+    }
+
+    fn weight(&self, instability: u32) -> f32 {
+        per_level([4., 4., 3., 3., 3.], instability)
+    }
+
+    fn duration(&self, _instability: u32) -> u32 {
+        GLITCH_SPEED * 6
+    }
+
+    fn start(&mut self, world: &mut World, _events: &mut Vec<Event>) -> bool {
+        let candidates: Vec<&Entity> = world
+            .enemies
+            .iter()
+            .filter(|b| {
+                b.pos.x > 0.
+                    && b.pos.x < WORLD_WIDTH
+                    && b.pos.y > 0.
+                    && b.pos.y < WORLD_HEIGHT
+                    && !b.is_clone
+            })
+            .collect();
+        let Some(&original) = world.rng.choose(&candidates) else {
+            return false;
+        };
+
+        self.original = Some(original.clone());
+        self.x_direction = world.rng.gen_range(-1, 1);
+        self.y_direction = world.rng.gen_range(-1, 1);
+        true
+    }
+
+    fn tick(&mut self, world: &mut World, elapsed: u32, events: &mut Vec<Event>) {
+        let Some(original) = &mut self.original else {
+            return;
+        };
+        original.tick(world.player.pos, None);
+
+        if elapsed % GLITCH_SPEED == GLITCH_SPEED - 1 {
+            let mut clone = original.clone();
+            clone.pos.x += if self.x_direction < 0 { -0.3 } else { 0.3 };
+            clone.pos.y += if self.y_direction < 0 { -0.3 } else { 0.3 };
+            clone.is_clone = true;
+            clone.prev_pos = clone.pos;
+            events.push(Event::GlitchSpawned { pos: clone.pos });
+            world.enemies.push(clone.clone());
+            *original = clone;
+        }
+    }
+
+    fn stop(&mut self, _world: &mut World) {
+        self.original = None;
+    }
+}
+
+pub struct InvertedControls;
+
+impl Glitch for InvertedControls {
+    fn name(&self) -> &'static str {
+        "inverted_controls"
+    }
+
+    fn weight(&self, instability: u32) -> f32 {
+        per_level([0., 0., 1., 2., 3.], instability)
+    }
+
+    fn duration(&self, instability: u32) -> u32 {
+        90 + 30 * instability
+    }
+
+    fn start(&mut self, world: &mut World, _events: &mut Vec<Event>) -> bool {
+        world.glitch_flags.inverted_controls = true;
+        true
+    }
+
+    fn stop(&mut self, world: &mut World) {
+        world.glitch_flags.inverted_controls = false;
+    }
+}
+
+/// Moves the player somewhere random in the arena.
+pub struct Teleport;
+
+impl Glitch for Teleport {
+    fn name(&self) -> &'static str {
+        "teleport"
+    }
+
+    fn weight(&self, instability: u32) -> f32 {
+        per_level([0., 1., 1., 2., 2.], instability)
+    }
+
+    fn duration(&self, _instability: u32) -> u32 {
+        0
+    }
+
+    fn start(&mut self, world: &mut World, _events: &mut Vec<Event>) -> bool {
+        world.player.pos = entities::random_inside_pos(&mut world.rng);
+        world.player.prev_pos = world.player.pos;
+        world.player.speed = macroquad::math::Vec2::ZERO;
+        true
+    }
+}
+
+/// Enemies move `SURGE_FACTOR` times faster.
+pub struct SpeedSurge;
+
+impl Glitch for SpeedSurge {
+    fn name(&self) -> &'static str {
+        "speed_surge"
+    }
+
+    fn weight(&self, instability: u32) -> f32 {
+        per_level([0., 1., 2., 2., 3.], instability)
+    }
+
+    fn duration(&self, instability: u32) -> u32 {
+        60 + 15 * instability
+    }
+
+    fn tick(&mut self, world: &mut World, _elapsed: u32, _events: &mut Vec<Event>) {
+        for enemy in &mut world.enemies {
+            let extra = enemy.speed * (SURGE_FACTOR - 1.);
+            // Pathers stay on their path
+            if matches!(enemy.e_type, EntityType::Pather { .. }) {
+                enemy.follow_path(extra.length());
+            } else {
+                enemy.pos += extra;
+            }
+        }
+    }
+}
+
+/// Enemies are drawn with another enemy's sprite.
+pub struct SpriteSwap;
+
+impl Glitch for SpriteSwap {
+    fn name(&self) -> &'static str {
+        "sprite_swap"
+    }
+
+    fn weight(&self, instability: u32) -> f32 {
+        per_level([1., 1., 2., 2., 2.], instability)
+    }
+
+    fn duration(&self, _instability: u32) -> u32 {
+        180
+    }
+
+    fn start(&mut self, world: &mut World, _events: &mut Vec<Event>) -> bool {
+        // Any shift but a full turn
+        let Some(others) = world.archetypes.len().checked_sub(1).filter(|&n| n > 0) else {
+            return false;
+        };
+        world.glitch_flags.sprite_shift = 1 + (world.rng.next_u64() % others as u64) as usize;
+        true
+    }
+
+    fn stop(&mut self, world: &mut World) {
+        world.glitch_flags.sprite_shift = 0;
+    }
+}
+
+pub struct HudCorruption;
+
+impl Glitch for HudCorruption {
+    fn name(&self) -> &'static str {
+        "hud_corruption"
+    }
+
+    fn weight(&self, instability: u32) -> f32 {
+        per_level([1., 1., 1., 2., 2.], instability)
+    }
+
+    fn duration(&self, instability: u32) -> u32 {
+        120 + 30 * instability
+    }
+
+    fn start(&mut self, world: &mut World, _events: &mut Vec<Event>) -> bool {
+        world.glitch_flags.hud_corrupted = true;
+        true
+    }
+
+    fn stop(&mut self, world: &mut World) {
+        world.glitch_flags.hud_corrupted = false;
+    }
+}
+
+pub struct FrozenFrames;
+
+impl Glitch for FrozenFrames {
+    fn name(&self) -> &'static str {
+        "frozen_frames"
+    }
+
+    fn weight(&self, instability: u32) -> f32 {
+        per_level([0., 0., 1., 1., 2.], instability)
+    }
+
+    fn duration(&self, instability: u32) -> u32 {
+        10 + 5 * instability
+    }
+
+    fn start(&mut self, world: &mut World, _events: &mut Vec<Event>) -> bool {
+        world.glitch_flags.frozen = true;
+        true
+    }
+
+    fn stop(&mut self, world: &mut World) {
+        world.glitch_flags.frozen = false;
+    }
+}
@@ -0,0 +1,270 @@
+use macroquad::prelude::*;
+use nanoserde::{DeJson, SerJson};
+
+/// Player input for a single simulation tick.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct Input {
+    pub up: bool,
+    pub down: bool,
+    pub left: bool,
+    pub right: bool,
+    pub power: bool,
+    /// Ability slot picked with the number keys, from 1, 0 if none
+    pub select: u8,
+}
+
+/// What the rebindable keys do.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Control {
+    Up,
+    Down,
+    Left,
+    Right,
+    Power,
+}
+
+impl Control {
+    pub const ALL: [Self; 5] = [Self::Up, Self::Down, Self::Left, Self::Right, Self::Power];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Up => "Move up",
+            Self::Down => "Move down",
+            Self::Left => "Move left",
+            Self::Right => "Move right",
+            Self::Power => "Use ability",
+        }
+    }
+
+    /// Stands for the bound key in texts, see `Bindings::fill_in`.
+    const fn placeholder(self) -> &'static str {
+        match self {
+            Self::Up => "{up}",
+            Self::Down => "{down}",
+            Self::Left => "{left}",
+            Self::Right => "{right}",
+            Self::Power => "{power}",
+        }
+    }
+}
+
+/// Keys that can be bound, with the names they're saved and shown with. The
+/// number keys stay on the ability slots and the backtick on the console.
+const KEY_NAMES: [(KeyCode, &str); 37] = [
+    (KeyCode::A, "A"),
+    (KeyCode::B, "B"),
+    (KeyCode::C, "C"),
+    (KeyCode::D, "D"),
+    (KeyCode::E, "E"),
+    (KeyCode::F, "F"),
+    (KeyCode::G, "G"),
+    (KeyCode::H, "H"),
+    (KeyCode::I, "I"),
+    (KeyCode::J, "J"),
+    (KeyCode::K, "K"),
+    (KeyCode::L, "L"),
+    (KeyCode::M, "M"),
+    (KeyCode::N, "N"),
+    (KeyCode::O, "O"),
+    (KeyCode::P, "P"),
+    (KeyCode::Q, "Q"),
+    (KeyCode::R, "R"),
+    (KeyCode::S, "S"),
+    (KeyCode::T, "T"),
+    (KeyCode::U, "U"),
+    (KeyCode::V, "V"),
+    (KeyCode::W, "W"),
+    (KeyCode::X, "X"),
+    (KeyCode::Y, "Y"),
+    (KeyCode::Z, "Z"),
+    (KeyCode::Up, "Up"),
+    (KeyCode::Down, "Down"),
+    (KeyCode::Left, "Left"),
+    (KeyCode::Right, "Right"),
+    (KeyCode::Space, "Space"),
+    (KeyCode::Enter, "Enter"),
+    (KeyCode::Tab, "Tab"),
+    (KeyCode::LeftShift, "LShift"),
+    (KeyCode::RightShift, "RShift"),
+    (KeyCode::LeftControl, "LCtrl"),
+    (KeyCode::RightControl, "RCtrl"),
+];
+
+/// `None` if the key can't be bound.
+pub fn key_name(key: KeyCode) -> Option<&'static str> {
+    KEY_NAMES
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, name)| *name)
+}
+
+fn key_from_name(name: &str) -> Option<KeyCode> {
+    KEY_NAMES
+        .iter()
+        .find(|(_, n)| *n == name)
+        .map(|(key, _)| *key)
+}
+
+/// The key of each `Control`, saved in the profile by key name.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, SerJson, DeJson)]
+#[nserde(proxy = "BindingNames")]
+pub struct Bindings {
+    keys: [KeyCode; 5],
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Self {
+            keys: [
+                KeyCode::W,
+                KeyCode::S,
+                KeyCode::A,
+                KeyCode::D,
+                KeyCode::Space,
+            ],
+        }
+    }
+}
+
+impl Bindings {
+    pub const fn key(self, control: Control) -> KeyCode {
+        self.keys[control as usize]
+    }
+
+    /// A key does one thing, the control it was bound to gets the previous
+    /// key of `control`.
+    pub fn bind(&mut self, control: Control, key: KeyCode) {
+        let previous = self.keys[control as usize];
+        if let Some(other) = self.keys.iter_mut().find(|k| **k == key) {
+            *other = previous;
+        }
+        self.keys[control as usize] = key;
+    }
+
+    /// `text` with the placeholders of each control, like `{up}`, replaced
+    /// by the name of its key.
+    pub fn fill_in(self, text: &str) -> String {
+        Control::ALL
+            .into_iter()
+            .fold(text.to_owned(), |text, control| {
+                text.replace(
+                    control.placeholder(),
+                    key_name(self.key(control)).unwrap_or("?"),
+                )
+            })
+    }
+
+    fn down(self, control: Control) -> bool {
+        is_key_down(self.key(control))
+    }
+}
+
+#[derive(SerJson, DeJson)]
+pub struct BindingNames {
+    keys: Vec<String>,
+}
+
+impl From<&Bindings> for BindingNames {
+    fn from(bindings: &Bindings) -> Self {
+        Self {
+            keys: bindings
+                .keys
+                .iter()
+                .map(|&key| key_name(key).unwrap_or_default().to_owned())
+                .collect(),
+        }
+    }
+}
+
+/// Unknown or missing names keep their default key.
+impl From<&BindingNames> for Bindings {
+    fn from(names: &BindingNames) -> Self {
+        let mut bindings = Self::default();
+        for (control, name) in Control::ALL.into_iter().zip(&names.keys) {
+            if let Some(key) = key_from_name(name) {
+                bindings.bind(control, key);
+            }
+        }
+        bindings
+    }
+}
+
+/// Number keys picking the ability slots, in order.
+const SLOT_KEYS: [KeyCode; 5] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+];
+
+/// The slot of the last number key pressed this frame, 0 if none.
+fn pressed_slot() -> u8 {
+    SLOT_KEYS
+        .iter()
+        .rposition(|&key| is_key_pressed(key))
+        .map_or(0, |i| i as u8 + 1)
+}
+
+impl Input {
+    pub fn from_keyboard(bindings: Bindings) -> Self {
+        Self {
+            up: bindings.down(Control::Up),
+            down: bindings.down(Control::Down),
+            left: bindings.down(Control::Left),
+            right: bindings.down(Control::Right),
+            power: is_key_pressed(bindings.key(Control::Power)),
+            select: pressed_slot(),
+        }
+    }
+
+    /// Packs the input into one byte, used by replays.
+    pub const fn to_bits(self) -> u8 {
+        self.up as u8
+            | (self.down as u8) << 1
+            | (self.left as u8) << 2
+            | (self.right as u8) << 3
+            | (self.power as u8) << 4
+            | (self.select & 0b111) << 5
+    }
+
+    pub const fn from_bits(bits: u8) -> Self {
+        Self {
+            up: bits & 1 != 0,
+            down: bits & 1 << 1 != 0,
+            left: bits & 1 << 2 != 0,
+            right: bits & 1 << 3 != 0,
+            power: bits & 1 << 4 != 0,
+            select: bits >> 5,
+        }
+    }
+}
+
+/// Turns per-frame keyboard state into per-tick `Input`s. Presses are kept
+/// until a tick consumes them, so they aren't lost on frames without a tick
+/// nor repeated on frames with several.
+#[derive(Default)]
+pub struct InputSampler {
+    power: bool,
+    select: u8,
+}
+
+impl InputSampler {
+    /// Call once per frame.
+    pub fn poll(&mut self, bindings: Bindings) {
+        self.power |= is_key_pressed(bindings.key(Control::Power));
+        let slot = pressed_slot();
+        if slot != 0 {
+            self.select = slot;
+        }
+    }
+
+    /// Input for the next tick.
+    pub fn next_tick(&mut self, bindings: Bindings) -> Input {
+        Input {
+            power: std::mem::take(&mut self.power),
+            select: std::mem::take(&mut self.select),
+            ..Input::from_keyboard(bindings)
+        }
+    }
+}
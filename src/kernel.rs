@@ -0,0 +1,127 @@
+use macroquad::math::Vec2;
+
+use crate::{
+    archetypes::{Archetypes, Behavior},
+    entities::{Entity, CENTER, WORLD_WIDTH},
+    paths::SplinePath,
+    rng::Rng,
+};
+
+/// Survival time, in seconds, before the Kernel shows up.
+pub const KERNEL_TIME: f32 = 90.;
+pub const KERNEL_HP: u32 = 6;
+const KERNEL_RADIUS: f32 = 3.;
+const KERNEL_SPEED: f32 = 0.08;
+const WEAK_POINT_SPEED: f32 = 0.015;
+/// Ticks between attacks, per phase.
+const ATTACK_INTERVAL: [u32; 3] = [150, 120, 90];
+
+/// The boss: a big process wandering in the top half of the arena. Its body
+/// hurts, and only `World::power_destroy` on its weak point hurts it.
+#[derive(Clone)]
+pub struct Kernel {
+    pub pos: Vec2,
+    pub prev_pos: Vec2,
+    pub radius: f32,
+    pub hp: u32,
+    /// Angle of the weak point around the body
+    pub rotation: f32,
+    pub hit_anim: u32,
+    age: u32,
+    attack_timer: u32,
+    attacks: u32,
+}
+
+impl Kernel {
+    pub fn new() -> Self {
+        let pos = Vec2::new(CENTER.x, -KERNEL_RADIUS * 2.);
+        Self {
+            pos,
+            prev_pos: pos,
+            radius: KERNEL_RADIUS,
+            hp: KERNEL_HP,
+            rotation: 0.,
+            hit_anim: 0,
+            age: 0,
+            attack_timer: ATTACK_INTERVAL[0],
+            attacks: 0,
+        }
+    }
+
+    /// 0 to 2, each phase adds an attack pattern and attacks more often.
+    pub const fn phase(&self) -> usize {
+        match self.hp {
+            5.. => 0,
+            3 | 4 => 1,
+            _ => 2,
+        }
+    }
+
+    pub fn weak_point(&self) -> Vec2 {
+        self.pos + Vec2::from_angle(self.rotation) * self.radius
+    }
+
+    /// Moves, and pushes the attacks of this tick into `enemies`. They use
+    /// the base archetypes and the swarm.
+    pub fn tick(
+        &mut self,
+        target_pos: Vec2,
+        archetypes: &Archetypes,
+        rng: &mut Rng,
+        enemies: &mut Vec<Entity>,
+    ) {
+        self.prev_pos = self.pos;
+        self.age += 1;
+        self.hit_anim = self.hit_anim.saturating_sub(1);
+        self.rotation += WEAK_POINT_SPEED * (self.phase() + 1) as f32;
+
+        let t = self.age as f32;
+        let slot = CENTER + Vec2::new((t * 0.01).sin() * 12., (t * 0.013).cos() * 3. - 6.);
+        self.pos += (slot - self.pos).clamp_length_max(KERNEL_SPEED);
+
+        if let Some(left) = self.attack_timer.checked_sub(1) {
+            self.attack_timer = left;
+            return;
+        }
+        self.attack_timer = ATTACK_INTERVAL[self.phase()];
+
+        // Cycles through the patterns unlocked so far
+        match self.attacks % (self.phase() as u32 + 1) {
+            0 => enemies.extend(Entity::bullet_ring(
+                archetypes.base(Behavior::Bullet),
+                self.pos,
+                12,
+                self.rotation,
+            )),
+            1 => {
+                for i in 0..4 {
+                    let angle = self.rotation + i as f32 * std::f32::consts::FRAC_PI_2;
+                    let swarm = archetypes.swarm();
+                    let mut follower = Entity::new_follower(
+                        swarm,
+                        self.weak_point(),
+                        Vec2::from_angle(angle) * 0.2,
+                        swarm.steering.expect("checked by Archetypes::from_ron"),
+                    );
+                    follower.speed += (target_pos - follower.pos).normalize_or_zero() * 0.05;
+                    enemies.push(follower);
+                }
+            }
+            _ => {
+                // Horizontal lanes sweeping across the arena
+                let from_left = rng.gen_range(0., 1.) < 0.5;
+                let (start, end) = if from_left {
+                    (-2., WORLD_WIDTH + 2.)
+                } else {
+                    (WORLD_WIDTH + 2., -2.)
+                };
+                for lane in 0..3 {
+                    let y = target_pos.y + (lane as f32 - 1.) * 4.;
+                    let path = SplinePath::through(&[Vec2::new(start, y), Vec2::new(end, y)]);
+                    enemies.push(Entity::new_pather(archetypes.base(Behavior::Pather), path));
+                }
+            }
+        }
+        self.attacks += 1;
+    }
+}
@@ -0,0 +1,49 @@
+// Everything but the frontend in `main.rs`, built as a library so the benches
+// run the same code as the game.
+
+#![warn(clippy::pedantic, clippy::nursery)]
+#![allow(
+    clippy::future_not_send,
+    clippy::too_many_lines,
+    clippy::cognitive_complexity,
+    clippy::cast_precision_loss,
+    clippy::cast_lossless,
+    clippy::cast_sign_loss,
+    clippy::cast_possible_truncation,
+    clippy::must_use_candidate,
+    clippy::missing_panics_doc,
+    clippy::missing_errors_doc,
+    clippy::new_without_default,
+    clippy::struct_excessive_bools,
+    clippy::suboptimal_flops,
+    // Triggered by nanoserde derives
+    clippy::question_mark,
+    clippy::collection_is_never_read
+)]
+
+pub mod abilities;
+pub mod achievements;
+pub mod archetypes;
+pub mod console;
+pub mod debugger;
+pub mod desktop;
+pub mod dialog;
+pub mod director;
+pub mod entities;
+pub mod events;
+pub mod glitches;
+pub mod input;
+pub mod kernel;
+pub mod paths;
+pub mod profile;
+pub mod replay;
+pub mod resources;
+pub mod rng;
+pub mod settings;
+pub mod spatial;
+pub mod steering;
+pub mod storage;
+#[cfg(test)]
+mod testing;
+pub mod timestep;
+pub mod world;
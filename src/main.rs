@@ -6,33 +6,68 @@
     clippy::cast_precision_loss,
     clippy::cast_lossless,
     clippy::cast_sign_loss,
-    clippy::cast_possible_truncation
+    clippy::cast_possible_truncation,
+    clippy::must_use_candidate,
+    clippy::missing_panics_doc,
+    clippy::missing_errors_doc,
+    clippy::new_without_default,
+    clippy::struct_excessive_bools,
+    clippy::suboptimal_flops,
+    // Triggered by nanoserde derives
+    clippy::question_mark,
+    clippy::collection_is_never_read
 )]
 
-pub mod achievements;
-pub mod entities;
-pub mod resources;
-pub mod world;
+use std::f32::consts::PI;
 
-use std::{f32::consts::PI, u8};
+use upsi3::{
+    abilities, archetypes, console, debugger, desktop, dialog, director, entities, events, input,
+    kernel, profile, replay, resources, rng, settings, timestep, world,
+};
 
-use entities::{EntityType, WORLD_WIDTH};
-use macroquad::audio::stop_sound;
+use abilities::{REFLECT_RANGE, SLOW_RANGE};
+use archetypes::Archetypes;
+use console::{Command, Console};
+use debugger::Debugger;
+use desktop::{Action, App, Tray, WindowManager};
+use dialog::{DialogAction, DialogQueue, PopupStyle, Trigger};
+use director::{Difficulty, WaveScript};
+use entities::{EntityType, ITEM_BLINK, WORLD_HEIGHT, WORLD_WIDTH};
+use events::{Event, ItemKind};
+use input::InputSampler;
+use kernel::KERNEL_HP;
+use macroquad::audio::{set_sound_volume, stop_sound};
 use macroquad::ui::{hash, root_ui, Skin};
 use macroquad::{
     audio::{play_sound, PlaySoundParams},
     prelude::*,
 };
+use profile::Profile;
+use replay::{Playback, Replay};
 use resources::Resources;
+use rng::Rng;
+use settings::{Settings, SettingsApp};
+use timestep::{FixedTimestep, TICK_DT};
 use world::{World, DESTROY_RANGE};
 
 const TITLE_BAR_HEIGHT: f32 = 60.;
-
+/// Base music volume, scaled by the music and master volume settings
+const MUSIC_VOLUME: f32 = 0.1;
+/// How far ahead of pathers their path is drawn, in world units
+const PATH_PREVIEW_LENGTH: f32 = 8.;
+/// Gap between the threat indicators and the edge of the screen, in world
+/// units
+const INDICATOR_MARGIN: f32 = 0.6;
+/// Indicators show up for enemies closer than this to the screen
+const INDICATOR_RANGE: f32 = 15.;
+/// How long before a pather enters the arena its entry point flashes
+const TELEGRAPH_TICKS: f32 = 45.;
+
+/// Apps live in the windows of `desktop::WindowManager`, the BSOD takes over
+/// the whole screen.
 #[derive(Clone, PartialEq, Eq)]
 pub enum GameState {
     Desktop,
-    Game,
-    Achievements,
     BSOD,
 }
 
@@ -89,23 +124,6 @@ impl UIElement {
     }
 }
 
-#[derive(Clone)]
-pub enum PopupStyle {
-    INFO,
-    WARNING,
-    ERROR,
-}
-
-impl PopupStyle {
-    pub fn get_name(&self) -> &str {
-        match self {
-            PopupStyle::ERROR => "Error",
-            PopupStyle::WARNING => "Warning",
-            PopupStyle::INFO => "Info",
-        }
-    }
-}
-
 #[derive(Clone)]
 pub struct Popup {
     pub button: UIElement,
@@ -114,12 +132,12 @@ pub struct Popup {
     pub height: f32,
     pub style: PopupStyle,
     pub visible: bool,
-    pub text: &'static str,
+    pub text: String,
 }
 
 impl Popup {
-    pub fn new() -> Popup {
-        Popup {
+    pub fn new() -> Self {
+        Self {
             button: UIElement::new(
                 vec2(screen_width() / 2. - 100., screen_height() / 2. + 100.),
                 vec2(200., 80.),
@@ -130,11 +148,12 @@ impl Popup {
             height: 400.,
             style: PopupStyle::INFO,
             visible: true,
-            text: "Some text here",
+            text: String::new(),
         }
     }
 
-    pub fn draw(&mut self, world: &mut World, game_state: &mut GameState, resources: &Resources) {
+    /// Returns `true` when the OK button is clicked.
+    pub fn draw(&mut self) -> bool {
         if self.visible {
             draw_rectangle(
                 0.,
@@ -189,7 +208,7 @@ impl Popup {
             );
 
             draw_text(
-                self.text,
+                &self.text,
                 self.position.x + 20.,
                 self.position.y + TITLE_BAR_HEIGHT + 50.,
                 30.,
@@ -203,84 +222,10 @@ impl Popup {
                 && self.button.collide(Vec2::new(mouse_x, mouse_y))
             {
                 self.visible = false;
-
-                if world.show_tutorial_1 {
-                    world.show_tutorial_1 = false;
-                    world.show_tutorial_2 = true;
-                } else if world.show_tutorial_2 {
-                    world.show_tutorial_2 = false;
-                    world.show_tutorial_3 = true;
-                } else if world.show_tutorial_3 {
-                    world.show_tutorial_3 = false;
-                    world.show_tutorial_4 = true;
-                } else if world.show_tutorial_4 {
-                    world.show_tutorial_4 = false;
-                    world.show_tutorial_5 = true;
-                } else if world.show_tutorial_5 {
-                    world.show_tutorial_5 = false;
-                } else if world.show_tutorial_2_1 {
-                    world.show_tutorial_2_1 = false;
-                    world.show_tutorial_2_2 = true;
-                } else if world.show_tutorial_2_2 {
-                    world.show_tutorial_2_2 = false;
-                    world.show_tutorial_2_3 = true;
-                } else if world.show_tutorial_2_3 {
-                    world.show_tutorial_2_3 = false;
-                    world.show_tutorial_2_4 = true;
-                } else if world.show_tutorial_2_4 {
-                    world.show_tutorial_2_4 = false;
-                    world.show_tutorial_2_5 = true;
-                } else if world.show_tutorial_2_5 {
-                    world.show_tutorial_2_5 = false;
-                    world.show_tutorial_2_6 = true;
-                } else if world.show_tutorial_2_6 {
-                    world.show_tutorial_2_6 = false;
-                } else if world.show_input_popup {
-                    world.show_input_popup = false;
-                } else if world.show_credits_1 {
-                    world.show_credits_1 = false;
-                    world.show_credits_2 = true;
-                    play_sound(
-                        resources.or_did_you,
-                        PlaySoundParams {
-                            looped: false,
-                            volume: 0.5,
-                        },
-                    )
-                } else if world.show_credits_2 {
-                    world.show_credits_2 = false;
-                    world.show_final_bsod = true;
-                    world.bsod(game_state, &resources);
-                }
+                return true;
             }
         }
-    }
-}
-
-fn window_decorations(
-    state: &mut GameState,
-    cross: &mut UIElement,
-    title: &str,
-    world: &mut World,
-) {
-    draw_rectangle(0., 0., screen_width(), TITLE_BAR_HEIGHT, LIGHTGRAY);
-    cross.position = vec2(screen_width() - 5. - 50., 5.);
-    cross.draw();
-
-    draw_text(
-        title,
-        screen_width() / 2. - get_text_center(title, None, 40, 1., 0.).x,
-        TITLE_BAR_HEIGHT / 2. + 5.,
-        40.,
-        BLACK,
-    );
-
-    if !world.popup_shown() {
-        let (mouse_x, mouse_y) = mouse_position();
-        if is_mouse_button_pressed(MouseButton::Left) && cross.collide(Vec2::new(mouse_x, mouse_y))
-        {
-            *state = GameState::Desktop;
-        }
+        false
     }
 }
 
@@ -301,7 +246,7 @@ fn draw_bsod_text(message: &str) {
     y += y_diff;
     draw_text("", 50., y, font_size_bsod, WHITE);
     y += y_diff;
-    draw_text(&format!("{}", message), 50., y, font_size_bsod * 1.5, WHITE);
+    draw_text(message, 50., y, font_size_bsod * 1.5, WHITE);
     y += y_diff;
     draw_text("", 50., y, font_size_bsod, WHITE);
     y += y_diff;
@@ -441,22 +386,31 @@ fn draw_sprite(
     );
 }
 
-fn draw_ui(texture: Texture2D, mut pos: Vec2, mut radius: f32, screen_width: f32, rotation: f32) {
+fn draw_ui(texture: Texture2D, pos: Vec2, radius: f32, screen_width: f32, rotation: f32) {
+    draw_ui_tinted(texture, pos, radius, screen_width, rotation, WHITE);
+}
+
+fn draw_ui_tinted(
+    texture: Texture2D,
+    mut pos: Vec2,
+    mut radius: f32,
+    screen_width: f32,
+    rotation: f32,
+    color: Color,
+) {
     let scale = screen_width / WORLD_WIDTH;
 
     pos -= Vec2::new(radius, radius);
 
     pos *= scale;
 
-    pos.y += TITLE_BAR_HEIGHT;
-
     radius *= scale;
 
     draw_texture_ex(
         texture,
         pos.x,
         pos.y,
-        WHITE,
+        color,
         DrawTextureParams {
             dest_size: Some(Vec2::new(radius * 2., radius * 2.)),
             source: None,
@@ -506,110 +460,333 @@ impl GlitchEffect {
         }
     }
 
-    pub fn set(&mut self, count: u32, intensity_multiplicator: f32) {
+    pub const fn set(&mut self, count: u32, intensity_multiplicator: f32) {
         self.count = count;
         self.intensity_multiplicator = intensity_multiplicator;
         // self.texture = Texture2D::from_image(&get_screen_data());
     }
 
-    pub fn run(&mut self) {
+    /// The glitch screen subscriber: shakes harder the more unstable the
+    /// world is.
+    pub const fn on_event(&mut self, event: &Event) {
+        match event {
+            Event::Unstable { instability } => {
+                let intensity = match instability {
+                    1 => 0.5,
+                    2 => 1.,
+                    3 => 2.,
+                    4 => 4.,
+                    _ => 8.,
+                };
+                self.set(20, intensity);
+            }
+            Event::Bsod(_) => self.set(20, 2.),
+            _ => (),
+        }
+    }
+
+    /// Scaled by the glitch intensity setting, without the color flicker
+    /// when flashing is reduced.
+    pub fn run(&mut self, settings: &Settings) {
         if self.count > 0 {
+            self.count -= 1;
+            let intensity = self.intensity_multiplicator * settings.glitch_intensity;
+            if intensity <= 0. {
+                return;
+            }
             update_texture_screen_foo_bar(&mut self.texture);
 
-            draw_texture_ex(
-                self.texture,
-                rand::RandomRange::gen_range(-5., 5.) * self.intensity_multiplicator,
-                rand::RandomRange::gen_range(-5., 5.) * self.intensity_multiplicator,
+            let tint = if settings.reduced_flashing {
+                Color::new(1., 1., 1., 0.3)
+            } else {
                 Color {
                     r: (rand::RandomRange::gen_range(0.5, 1.)),
                     g: (rand::RandomRange::gen_range(0.5, 1.)),
                     b: (rand::RandomRange::gen_range(0.5, 1.)),
                     a: (0.3),
-                },
+                }
+            };
+            draw_texture_ex(
+                self.texture,
+                rand::RandomRange::gen_range(-5., 5.) * intensity,
+                rand::RandomRange::gen_range(-5., 5.) * intensity,
+                tint,
                 DrawTextureParams {
                     dest_size: Some(vec2(screen_width(), screen_height())),
                     flip_x: false,
                     flip_y: true,
                     pivot: None,
                     source: None,
-                    rotation: (rand::RandomRange::gen_range(-PI / 96., PI / 96.))
-                        * self.intensity_multiplicator,
+                    rotation: (rand::RandomRange::gen_range(-PI / 96., PI / 96.)) * intensity,
                 },
             );
+        }
+    }
+}
 
-            self.count -= 1;
+/// A world drawn off screen at screen scale, then shown in its window. Keeps
+/// showing the last frame drawn while the world is frozen.
+pub struct GameView {
+    target: RenderTarget,
+    size: Vec2,
+    captured: bool,
+}
+
+impl GameView {
+    pub fn new() -> Self {
+        let size = vec2(screen_width(), screen_height());
+        let target = render_target(size.x as u32, size.y as u32);
+        target.texture.set_filter(FilterMode::Nearest);
+        Self {
+            target,
+            size,
+            captured: false,
+        }
+    }
+
+    pub fn render(
+        &mut self,
+        world: &World,
+        resources: &Resources,
+        alpha: f32,
+        director_overlay: bool,
+        reduced_flashing: bool,
+    ) {
+        let size = vec2(screen_width(), screen_height());
+        if size != self.size {
+            self.target.delete();
+            *self = Self::new();
+        }
+
+        if world.glitch_flags.frozen && self.captured {
+            return;
+        }
+        self.captured = world.glitch_flags.frozen;
+
+        set_camera(&Camera2D {
+            render_target: Some(self.target),
+            ..Camera2D::from_display_rect(Rect::new(0., 0., size.x, size.y))
+        });
+        clear_background(BLACK);
+        draw_game(world, resources, alpha, reduced_flashing);
+        if director_overlay {
+            draw_director_overlay(world);
+        }
+        set_default_camera();
+    }
+
+    /// Fits the last frame rendered in `rect`, keeping its aspect ratio.
+    pub fn draw(&self, rect: Rect) {
+        let scale = (rect.w / self.size.x).min(rect.h / self.size.y);
+        let size = self.size * scale;
+        let pos = rect.point() + (rect.size() - size) / 2.;
+        draw_texture_ex(
+            self.target.texture,
+            pos.x,
+            pos.y,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(size),
+                flip_y: true,
+                ..Default::default()
+            },
+        );
+    }
+}
+
+fn handle_events(
+    events: &mut Vec<Event>,
+    resources: &Resources,
+    game_state: &mut GameState,
+    bsod_message: &mut String,
+    glitch_effect: &mut GlitchEffect,
+    profile: &mut Profile,
+) {
+    for event in events.drain(..) {
+        if let Some(sfx) = event.sfx() {
+            let (sound, volume) = resources.sfx(sfx);
+            play_sound(
+                sound,
+                PlaySoundParams {
+                    looped: false,
+                    volume: volume * profile.settings.sfx_volume(),
+                },
+            );
+        }
+
+        glitch_effect.on_event(&event);
+
+        match event {
+            Event::Bsod(message) => {
+                *game_state = GameState::BSOD;
+                *bsod_message = message;
+                stop_sound(resources.or_did_you);
+                play_sound(
+                    resources.bsod_sound,
+                    PlaySoundParams {
+                        looped: false,
+                        volume: profile.settings.sfx_volume(),
+                    },
+                );
+            }
+            Event::AchievementUnlocked(id) => {
+                profile.unlock(&id, miniquad::date::now());
+                profile.save();
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Applies what changed from `previous` on the window and the music already
+/// playing, the rest is read from the settings when used.
+fn apply_settings(previous: &Settings, settings: &Settings, resources: &Resources) {
+    if settings.fullscreen != previous.fullscreen {
+        set_fullscreen(settings.fullscreen);
+    }
+    if (settings.music_volume() - previous.music_volume()).abs() > f32::EPSILON {
+        set_sound_volume(resources.music, MUSIC_VOLUME * settings.music_volume());
+    }
+}
+
+/// Toasts in the tray for what the player should notice.
+fn notify(tray: &mut Tray, events: &[Event], world: &World) {
+    for event in events {
+        match event {
+            Event::AchievementUnlocked(id) => {
+                let name = world.achievements.get(id).map_or(id.as_str(), |a| &a.name);
+                tray.notify("Bug found", name);
+            }
+            Event::InstabilityRaised => tray.notify(
+                "System unstable",
+                &format!("Instability rose to {}", world.instability),
+            ),
+            _ => (),
         }
     }
 }
 
-fn draw_game(world: &World, resources: &Resources) {
-    let player_pos = world.player.pos;
+/// `alpha` is how far the frame is between the last tick and the next one,
+/// positions are interpolated so movement stays smooth at any frame rate.
+/// With `reduced_flashing` nothing blinks.
+fn draw_game(world: &World, resources: &Resources, alpha: f32, reduced_flashing: bool) {
+    let player_pos = world.player.draw_pos(alpha);
     let player_radius = world.player.radius;
 
+    let hit = if reduced_flashing {
+        world.player.hit_anim > 0
+    } else {
+        !world.player.hit_anim.is_multiple_of(2)
+    };
     draw_sprite(
-        if world.player.hit_anim % 2 == 0 {
-            resources.player
-        } else {
+        if hit {
             resources.player_hit
+        } else {
+            resources.player
         },
         player_pos,
         player_radius,
         screen_width(),
         world.player.rotation,
     );
+    draw_ability_effects(world, player_pos, reduced_flashing);
 
+    // Faint preview of where pathers are heading, clones follow the same
+    // path shifted aside
+    let scale = screen_width() / WORLD_WIDTH;
     for enemy in &world.enemies {
-        let texture = match enemy.e_type {
-            EntityType::Bullet => {
-                if enemy.is_clone {
-                    resources.bullet_glitch
-                } else {
-                    resources.bullet
-                }
-            }
-            EntityType::Follower => {
-                if enemy.is_clone {
-                    resources.follower_glitch
-                } else {
-                    resources.follower
-                }
-            }
-            EntityType::Pather(_) => {
-                if enemy.is_clone {
-                    resources.pather_glitch
-                } else {
-                    resources.pather
-                }
+        if let EntityType::Pather {
+            path, travelled, ..
+        } = &enemy.e_type
+        {
+            let offset = enemy.pos - path.point_at(*travelled);
+            let section: Vec<Vec2> = path
+                .section(*travelled, PATH_PREVIEW_LENGTH)
+                .map(|p| (p + offset) * scale)
+                .collect();
+            for segment in section.windows(2) {
+                draw_line(
+                    segment[0].x,
+                    segment[0].y,
+                    segment[1].x,
+                    segment[1].y,
+                    0.1 * scale,
+                    Color::new(0.3, 0.9, 0.3, 0.15),
+                );
             }
-            _ => unreachable!(),
-        };
+        }
+    }
+
+    for enemy in &world.enemies {
+        // Blinks while a tanky enemy recovers from a hit
+        if enemy.hit_anim % 4 >= 2 && !reduced_flashing {
+            continue;
+        }
+        // Rotated by the sprite swap glitch
+        let sprites = &resources.enemies;
+        let (normal, glitched) =
+            sprites[(enemy.archetype + world.glitch_flags.sprite_shift) % sprites.len()];
+        let texture = if enemy.is_clone { glitched } else { normal };
 
         draw_sprite(
             texture,
-            enemy.pos,
+            enemy.draw_pos(alpha),
             enemy.radius,
             screen_width(),
             enemy.rotation,
         );
     }
 
+    if let Some(kernel) = &world.kernel {
+        let pos = kernel.prev_pos.lerp(kernel.pos, alpha);
+        // Blinks while recovering from a hit, like the player
+        if kernel.hit_anim % 4 < 2 || reduced_flashing {
+            draw_sprite(resources.kernel, pos, kernel.radius, screen_width(), 0.);
+        }
+        draw_sprite(
+            resources.bug,
+            pos + (kernel.weak_point() - kernel.pos),
+            0.8,
+            screen_width(),
+            kernel.rotation,
+        );
+    }
+
     for item in &world.items {
-        let texture = match item.e_type {
-            EntityType::HealItem => resources.heart,
-            EntityType::ManaItem => resources.energy,
-            _ => unreachable!(),
+        let EntityType::Item { kind, lifetime } = item.e_type else {
+            unreachable!()
         };
+        // Blinks faster and faster before despawning
+        if lifetime < ITEM_BLINK && !reduced_flashing {
+            let period = 4 + lifetime / 10;
+            if lifetime % period < period / 2 {
+                continue;
+            }
+        }
 
         draw_sprite(
-            texture,
-            item.pos,
+            resources.item_sprites[kind as usize],
+            item.draw_pos(alpha),
             item.radius,
             screen_width(),
             item.rotation,
         );
     }
 
-    for i in 0..world.hp {
+    draw_spawn_telegraphs(world, reduced_flashing);
+    draw_threat_indicators(world, resources, alpha);
+
+    // Garbage numbers while the HUD is corrupted, the simulation isn't touched
+    let corrupted = world.glitch_flags.hud_corrupted;
+    let hud = |value: u32| {
+        if corrupted {
+            rand::gen_range(0, 10)
+        } else {
+            value
+        }
+    };
+
+    for i in 0..hud(world.hp as u32) {
         draw_ui(
             resources.heart,
             Vec2::new(1. + i as f32 * 0.8, 1.),
@@ -619,7 +796,7 @@ fn draw_game(world: &World, resources: &Resources) {
         );
     }
 
-    for i in 0..world.mana {
+    for i in 0..hud(world.mana as u32) {
         draw_ui(
             resources.energy,
             Vec2::new(4. + i as f32 * 0.8, 1.),
@@ -629,7 +806,7 @@ fn draw_game(world: &World, resources: &Resources) {
         );
     }
 
-    for i in 0..world.instability {
+    for i in 0..hud(world.instability) {
         draw_ui(
             resources.bug,
             Vec2::new(39. - i as f32 * 0.8, 1.),
@@ -639,6 +816,22 @@ fn draw_game(world: &World, resources: &Resources) {
         );
     }
 
+    if let Some(kernel) = &world.kernel {
+        let scale = screen_width() / WORLD_WIDTH;
+        let (x, y) = (12. * scale, 0.6 * scale);
+        let (width, height) = (16. * scale, 0.5 * scale);
+        draw_rectangle(x, y, width, height, DARKGRAY);
+        draw_rectangle(
+            x,
+            y,
+            width * kernel.hp as f32 / KERNEL_HP as f32,
+            height,
+            RED,
+        );
+        draw_rectangle_lines(x, y, width, height, 2., WHITE);
+        draw_text("KERNEL", x, y - 4., 0.6 * scale, WHITE);
+    }
+
     if world.power_up_timer > 0 {
         draw_sprite(
             resources.power_up,
@@ -649,21 +842,326 @@ fn draw_game(world: &World, resources: &Resources) {
         );
     }
 
+    draw_loadout(world);
+    draw_pickup_effects(world, resources);
+
+    let timer = if corrupted {
+        format!("{:#x}", rand::gen_range(0, u32::MAX))
+    } else {
+        format!("{}", world.timer as u32)
+    };
+    draw_ui_text(&timer, Vec2::new(1., 29.), 1.5, screen_width());
+
     draw_ui_text(
-        &format!("{}", world.timer as u32),
-        Vec2::new(1., 29.),
-        1.5,
+        &format!("seed {}", world.seed),
+        Vec2::new(33., 29.),
+        0.8,
         screen_width(),
     );
 }
 
+/// Color of an enemy type on the threat indicators, after its sprite.
+const fn threat_color(e_type: &EntityType) -> Color {
+    match e_type {
+        EntityType::Bullet => RED,
+        EntityType::Follower { .. } => YELLOW,
+        EntityType::Pather { .. } => GREEN,
+        EntityType::Splitter(_) => SKYBLUE,
+        EntityType::Orbiter { .. } => ORANGE,
+        _ => WHITE,
+    }
+}
+
+/// Arrows on the edge of the screen pointing at the enemies coming in from
+/// off-screen, bigger and more opaque the closer they are.
+fn draw_threat_indicators(world: &World, resources: &Resources, alpha: f32) {
+    let scale = screen_width() / WORLD_WIDTH;
+    // Visible part of the arena
+    let bottom = (screen_height() / scale).min(WORLD_HEIGHT);
+    let min = Vec2::splat(INDICATOR_MARGIN);
+    let max = Vec2::new(WORLD_WIDTH - INDICATOR_MARGIN, bottom - INDICATOR_MARGIN);
+
+    for enemy in &world.enemies {
+        let pos = enemy.draw_pos(alpha);
+        let edge = pos.clamp(min, max);
+        let outside = pos - edge;
+        let distance = outside.length();
+        // On screen, too far, or flying away
+        if distance <= INDICATOR_MARGIN
+            || distance > INDICATOR_RANGE
+            || enemy.speed.dot(outside) >= 0.
+        {
+            continue;
+        }
+
+        let closeness = 1. - distance / INDICATOR_RANGE;
+        let mut color = threat_color(&enemy.e_type);
+        color.a = 0.3 + 0.7 * closeness;
+        draw_ui_tinted(
+            resources.indicator,
+            edge,
+            0.25 + 0.25 * closeness,
+            screen_width(),
+            outside.y.atan2(outside.x),
+            color,
+        );
+    }
+}
+
+/// Rings around the player for the abilities still in effect.
+fn draw_ability_effects(world: &World, player_pos: Vec2, reduced_flashing: bool) {
+    let scale = screen_width() / WORLD_WIDTH;
+    let pos = player_pos * scale;
+    let effects = &world.effects;
+
+    if effects.slow > 0 {
+        draw_circle(
+            pos.x,
+            pos.y,
+            SLOW_RANGE * scale,
+            Color::new(0.3, 0.5, 1., 0.08),
+        );
+        draw_circle_lines(
+            pos.x,
+            pos.y,
+            SLOW_RANGE * scale,
+            0.05 * scale,
+            Color::new(0.3, 0.5, 1., 0.4),
+        );
+    }
+    if effects.reflector > 0 {
+        draw_circle_lines(
+            pos.x,
+            pos.y,
+            REFLECT_RANGE * scale,
+            0.08 * scale,
+            Color::new(1., 0.8, 0.2, 0.6),
+        );
+    }
+    // Blinks when about to run out
+    let blink_off = effects.shield <= 30 && effects.shield % 8 < 4 && !reduced_flashing;
+    if effects.shield > 0 && !blink_off {
+        draw_circle_lines(
+            pos.x,
+            pos.y,
+            (world.player.radius + 0.3) * scale,
+            0.12 * scale,
+            SKYBLUE,
+        );
+    }
+    if effects.dash > 0 {
+        let trail = player_pos - world.player.speed * 3.;
+        draw_circle(
+            trail.x * scale,
+            trail.y * scale,
+            world.player.radius * scale,
+            Color::new(1., 1., 1., 0.2),
+        );
+    }
+
+    for enemy in world.enemies.iter().filter(|e| e.friendly) {
+        let pos = enemy.pos * scale;
+        draw_circle_lines(pos.x, pos.y, enemy.radius * scale, 0.06 * scale, GOLD);
+    }
+}
+
+/// Pickups still in effect under the hp and mana, with the seconds left.
+fn draw_pickup_effects(world: &World, resources: &Resources) {
+    let effects = &world.effects;
+    let active = [
+        (ItemKind::Score, effects.score),
+        (ItemKind::Speed, effects.speed),
+        (ItemKind::Magnet, effects.magnet),
+    ];
+    for (i, (kind, ticks)) in active.into_iter().filter(|(_, t)| *t > 0).enumerate() {
+        let pos = Vec2::new(1. + i as f32 * 2., 2.);
+        draw_ui(
+            resources.item_sprites[kind as usize],
+            pos,
+            0.3,
+            screen_width(),
+            0.,
+        );
+        let scale = screen_width() / WORLD_WIDTH;
+        draw_text(
+            &format!("{}", (ticks as f32 * TICK_DT).ceil()),
+            (pos.x + 0.4) * scale,
+            (pos.y + 0.25) * scale,
+            0.6 * scale,
+            WHITE,
+        );
+    }
+
+    draw_ui_text(
+        &format!("score {}", world.score),
+        Vec2::new(1., 27.5),
+        0.8,
+        screen_width(),
+    );
+}
+
+/// Ability slots at the bottom of the screen, the selected one highlighted
+/// and the cooling down ones filling back up.
+fn draw_loadout(world: &World) {
+    let scale = screen_width() / WORLD_WIDTH;
+    let (width, height) = (4.5 * scale, 1.2 * scale);
+    let y = 27.2 * scale;
+
+    for (i, (name, cost, left, cooldown)) in world.loadout.slots().enumerate() {
+        let x = (8. + i as f32 * 5.) * scale;
+        let selected = i == world.loadout.selected;
+        draw_rectangle(x, y, width, height, Color::new(0., 0., 0., 0.5));
+        if left > 0 {
+            let ready = 1. - left as f32 / cooldown as f32;
+            draw_rectangle(x, y, width * ready, height, Color::new(1., 1., 1., 0.15));
+        }
+        draw_rectangle_lines(
+            x,
+            y,
+            width,
+            height,
+            if selected { 3. } else { 1. },
+            if selected { YELLOW } else { GRAY },
+        );
+        draw_text(
+            &format!("{} {name} ({cost})", i + 1),
+            x + 0.2 * scale,
+            y + 0.8 * scale,
+            0.6 * scale,
+            if left > 0 { GRAY } else { WHITE },
+        );
+    }
+}
+
+/// Flashes where pathers are about to enter the arena.
+fn draw_spawn_telegraphs(world: &World, reduced_flashing: bool) {
+    if ((get_time() * 8.) as u32).is_multiple_of(2) && !reduced_flashing {
+        return;
+    }
+    let scale = screen_width() / WORLD_WIDTH;
+    let arena = Vec2::new(WORLD_WIDTH, WORLD_HEIGHT);
+
+    for enemy in &world.enemies {
+        let EntityType::Pather {
+            path,
+            travelled,
+            pace,
+        } = &enemy.e_type
+        else {
+            continue;
+        };
+        let offset = enemy.pos - path.point_at(*travelled);
+        if (enemy.pos.cmpge(Vec2::ZERO) & enemy.pos.cmple(arena)).all() {
+            continue;
+        }
+        let Some(entry) = path.entry(*travelled, -offset, arena - offset) else {
+            continue;
+        };
+        if entry - travelled > pace * TELEGRAPH_TICKS {
+            continue;
+        }
+
+        let pos = (path.point_at(entry) + offset) * scale;
+        draw_circle_lines(pos.x, pos.y, 0.8 * scale, 0.12 * scale, GREEN);
+    }
+}
+
+/// Director state, toggled with F3 while playing.
+fn draw_director_overlay(world: &World) {
+    let director = &world.director;
+    let wave = director.current_wave();
+    let lines = [
+        format!("difficulty  {}", director.difficulty.name()),
+        format!(
+            "wave        #{} {} (loop {})",
+            director.wave, wave.name, director.loops
+        ),
+        format!(
+            "wave time   {:.1}/{:.1}s",
+            director.wave_time, wave.duration
+        ),
+        format!("density     {:.2}", director.density),
+        format!("mercy       {:.2}", director.mercy),
+        format!("recent hits {:.2}", director.recent_hits),
+        format!("enemies     {}", world.enemies.len()),
+    ];
+
+    let x = 10.;
+    let mut y = 100.;
+    draw_rectangle(
+        x - 5.,
+        y - 20.,
+        320.,
+        lines.len() as f32 * 22. + 10.,
+        Color::new(0., 0., 0., 0.6),
+    );
+    for line in &lines {
+        draw_text(line, x, y, 22., GREEN);
+        y += 22.;
+    }
+}
+
+/// Content of the Achievements window.
+fn draw_achievements(world: &World, resources: &Resources, profile: &Profile, rect: Rect) {
+    let ach_x = rect.x + 20.;
+    let mut ach_y = rect.y + 10.;
+
+    for ach in &world.achievements.achievements {
+        ach.draw(
+            vec2(ach_x, ach_y),
+            rect.w - 40.,
+            resources.achievement_icon(ach),
+        );
+        ach_y += 50.;
+    }
+
+    draw_text(
+        &format!("Best time: {}s", profile.best_time as u32),
+        ach_x,
+        ach_y + 30.,
+        30.,
+        WHITE,
+    );
+}
+
+/// `--seed <n>` replays a shared run, e.g. `upsi3 --seed 1234`.
+fn seed_from_args() -> Option<u64> {
+    let mut args = std::env::args().skip_while(|arg| arg != "--seed").skip(1);
+    args.next()?.parse().ok()
+}
+
 #[macroquad::main("Unglitched")]
 async fn main() {
-    let mut world = World::new();
+    let seed = seed_from_args().unwrap_or_else(|| {
+        let mut rng = Rng::new(miniquad::date::now().to_bits());
+        rng.next_u64() % 10_000
+    });
+    let archetypes = Archetypes::load();
+    let script = WaveScript::load(&archetypes);
+    let mut world = World::with_seed(seed, archetypes, script);
+    let mut events = Vec::new();
+
+    let resources = Resources::load(&world.achievements, &world.archetypes).await;
+
+    let mut profile = Profile::load();
+    profile.apply(&mut world.achievements);
+    if profile.settings.fullscreen {
+        set_fullscreen(true);
+    }
+    // Picked in the Settings app, applied when a run starts
+    let mut difficulty = Difficulty::from_name(&profile.difficulty).unwrap_or_default();
+    let mut director_overlay = false;
 
-    let resources = Resources::load().await;
+    let mut input_text = profile.player_name.clone();
 
-    let mut input_text = String::new();
+    let mut dialogs = DialogQueue::load();
+    dialogs.seen = profile
+        .seen_dialogs
+        .iter()
+        .filter_map(|name| Trigger::from_name(name))
+        .collect();
+    dialogs.trigger(Trigger::FirstLaunch, &mut events);
+    let mut final_bsod = false;
 
     let mut wallpaper = UIElement::new(
         vec2(0., 0.),
@@ -683,22 +1181,58 @@ async fn main() {
         include_bytes!("../assets/images/icon_ach.png"),
     );
 
-    let mut cross = UIElement::new(
-        vec2(screen_width() - 5. - 50., 5.),
+    let mut icon_rpl = UIElement::new(
+        vec2(20., 220.),
+        vec2(64., 80.),
+        include_bytes!("../assets/images/icon_rpl.png"),
+    );
+
+    let mut icon_dbg = UIElement::new(
+        vec2(20., 320.),
+        vec2(64., 80.),
+        include_bytes!("../assets/images/icon_dbg.png"),
+    );
+    let mut debugger = Debugger::default();
+
+    let mut icon_set = UIElement::new(
+        vec2(20., 420.),
+        vec2(64., 80.),
+        include_bytes!("../assets/images/icon_set.png"),
+    );
+    let mut settings_app = SettingsApp::default();
+    // Saved once the mouse is released, not on every step of a slider
+    let mut settings_unsaved = false;
+
+    let cross = UIElement::new(
+        vec2(0., 0.),
         vec2(50., 50.),
         include_bytes!("../assets/images/cross.png"),
     );
 
     let mut game_state = GameState::Desktop;
-    let mut last_game_state = game_state.clone();
+    let mut windows = WindowManager::default();
+    let mut tray = Tray::default();
+    let mut shut_down = false;
+    let mut game_was_open = false;
 
     let mut bsod_message = "Overflow on name input".to_owned();
 
-    // let mut glitch_effect = GlitchEffect::new();
+    let mut glitch_effect = GlitchEffect::new();
+    let mut game_view = GameView::new();
+    let mut replay_view = GameView::new();
+
+    let mut timestep = FixedTimestep::default();
+    let mut replay_timestep = FixedTimestep::default();
+    let mut input = InputSampler::default();
+
+    let mut recording = Replay::default();
+    let mut playback: Option<Playback> = None;
 
     let mut popup = Popup::new();
     popup.visible = true;
 
+    let mut console = Console::new();
+
     let skin = {
         let editbox_style = root_ui()
             .style_builder()
@@ -716,177 +1250,341 @@ async fn main() {
         clear_background(BLACK);
         wallpaper.draw_dst = vec2(screen_width(), screen_height());
 
+        match console.update(&mut world, &mut profile, &mut events) {
+            Some(Command::Seed(_)) => recording = Replay::new(&world),
+            Some(command) if command.cheats() => recording.tainted = true,
+            _ => (),
+        }
+
         match game_state {
             GameState::Desktop => {
-                wallpaper.draw();
-                icon_ung.draw();
-                icon_ach.draw();
+                let mouse = Vec2::from(mouse_position());
+                if !dialogs.is_shown() {
+                    // Clicks on windows don't go through to the icons behind
+                    let on_desktop =
+                        is_mouse_button_pressed(MouseButton::Left) && !windows.covers(mouse);
+                    let mut launch = None;
+                    match windows.update(mouse) {
+                        Some(Action::Closed(App::Replay)) => playback = None,
+                        Some(Action::Launch(app)) => launch = Some(app),
+                        Some(Action::ShutDown) => shut_down = true,
+                        Some(Action::Closed(_)) | None => (),
+                    }
 
-                if !world.popup_shown() {
-                    let (mouse_x, mouse_y) = mouse_position();
-                    if is_mouse_button_pressed(MouseButton::Left)
-                        && icon_ung.collide(Vec2::new(mouse_x, mouse_y))
-                    {
-                        game_state = GameState::Game;
-                        world.show_input_popup = true;
+                    let clicked = |icon: &UIElement| on_desktop && icon.collide(mouse);
+                    for (icon, app) in [
+                        (&icon_ung, App::Unglitched),
+                        (&icon_ach, App::Achievements),
+                        (&icon_rpl, App::Replay),
+                        (&icon_dbg, App::Debugger),
+                        (&icon_set, App::Settings),
+                    ] {
+                        if clicked(icon) {
+                            launch = Some(app);
+                        }
                     }
 
-                    if is_mouse_button_pressed(MouseButton::Left)
-                        && icon_ach.collide(Vec2::new(mouse_x, mouse_y))
-                    {
-                        game_state = GameState::Achievements;
+                    match launch {
+                        Some(App::Replay) => {
+                            let replay = Replay::load().unwrap_or_default();
+                            if !replay.inputs.is_empty() {
+                                match Playback::new(
+                                    replay,
+                                    world.archetypes.clone(),
+                                    world.director.script.clone(),
+                                ) {
+                                    Some(loaded) => {
+                                        playback = Some(loaded);
+                                        replay_timestep.reset();
+                                        windows.open(App::Replay);
+                                    }
+                                    None => tray.notify(
+                                        "Replay",
+                                        "The last run was recorded with other enemies or waves",
+                                    ),
+                                }
+                            }
+                        }
+                        Some(app) => {
+                            let opened = windows.open(app);
+                            if opened && app == App::Unglitched {
+                                // A new run, started once the name is entered
+                                world.reset();
+                                world.has_game_started = false;
+                                dialogs.trigger(Trigger::NameInput, &mut events);
+                            }
+                        }
+                        None => (),
                     }
                 }
-            }
-
-            GameState::Game => {
-                if world.has_game_started {
-                    world.tick(&resources, &mut game_state, &mut bsod_message);
-                    draw_game(&world, &resources);
-                } else {
-                    popup.style = PopupStyle::INFO;
+                tray.update(get_frame_time());
+                // However it was closed, the game doesn't stay paused without
+                // a way to resume it
+                if debugger.paused && !windows.is_open(App::Debugger) {
+                    debugger = Debugger::default();
+                }
 
-                    if !world.show_input_popup {
+                if let Some(window) = windows.window(App::Unglitched) {
+                    let naming = dialogs.current_trigger() == Some(Trigger::NameInput);
+                    let focused = windows.focused() == Some(App::Unglitched);
+                    // Keeps running while inspected, the console has the keyboard
+                    let active =
+                        (focused || windows.focused() == Some(App::Debugger)) && !console.open;
+
+                    if world.has_game_started {
+                        if active {
+                            input.poll(profile.settings.bindings);
+                            if is_key_pressed(KeyCode::F3) {
+                                director_overlay = !director_overlay;
+                            }
+                        }
+                        // Background and minimized games are paused, as well as
+                        // the ones paused by the debugger
+                        let ticks = if debugger.paused {
+                            timestep.reset();
+                            u32::from(debugger.take_step())
+                        } else if active {
+                            timestep.advance(get_frame_time() * console.timescale)
+                        } else {
+                            timestep.reset();
+                            0
+                        };
+                        tray.play_time += ticks as f32 * TICK_DT;
+                        for _ in 0..ticks {
+                            let tick_input = input.next_tick(profile.settings.bindings);
+                            recording.record(tick_input);
+                            let first_event = events.len();
+                            world.tick(&tick_input, &mut events);
+                            // The world was reset, the rest of the frame
+                            // belongs to the BSOD
+                            if events[first_event..]
+                                .iter()
+                                .any(|e| matches!(e, Event::Bsod(_)))
+                            {
+                                timestep.reset();
+                                break;
+                            }
+                        }
+                        profile.best_time = profile.best_time.max(world.timer);
+                    } else if !naming {
                         world.has_game_started = true;
+                        profile.player_name.clone_from(&input_text);
+                        world.director.difficulty = difficulty;
+                        profile.save();
 
                         if input_text.len() > 8 {
-                            if world.achievements.achievements[0].unlocked {
-                                world.raise_unstability(&resources);
-                            } else {
-                                world.achievements.achievements[0].unlock();
-                                bsod_message = world.achievements.achievements[0].name.to_string();
-                                // game_state = GameState::BSOD;
-                                // play_sound(resources.bsod_sound, PlaySoundParams::default());
-
-                                world.bsod(&mut game_state, &resources);
-                                world.glitch_effect.set(20, 2.);
-                            }
+                            world.trigger_bug("name_overflow", &mut events);
                         }
-                    }
-                }
-                if world.show_input_popup {
-                    world.reset();
-                    world.has_game_started = false;
-
-                    root_ui().push_skin(&skin);
-                    root_ui().window(
-                        hash!(),
-                        vec2(screen_width() / 2. - 250., screen_height() / 2.),
-                        vec2(500., 45.),
-                        |ui| {
-                            ui.input_text(hash!(), "", &mut input_text);
-                        },
-                    );
-                    // root_ui().pop_skin();
-                    // root_ui().close_current_window();
-                }
 
-                window_decorations(&mut game_state, &mut cross, "Unglitched", &mut world);
-            }
+                        recording = Replay::new(&world);
+                    }
+                    if !window.minimized {
+                        // Shows exactly the last tick while stepping
+                        let alpha = if debugger.paused {
+                            1.
+                        } else {
+                            timestep.alpha()
+                        };
+                        game_view.render(
+                            &world,
+                            &resources,
+                            alpha,
+                            director_overlay,
+                            profile.settings.reduced_flashing,
+                        );
+                    }
 
-            GameState::Achievements => {
-                let ach_x = 50.;
-                let mut ach_y = TITLE_BAR_HEIGHT + 10.;
+                    if naming && focused {
+                        let center = window.content().center();
+                        root_ui().push_skin(&skin);
+                        root_ui().window(
+                            hash!(),
+                            vec2(center.x - 250., center.y),
+                            vec2(500., 45.),
+                            |ui| {
+                                ui.input_text(hash!(), "", &mut input_text);
+                            },
+                        );
+                        // root_ui().pop_skin();
+                        // root_ui().close_current_window();
+                    }
+                }
 
-                let mut cl_ach = world.achievements.clone();
+                notify(&mut tray, &events, &world);
 
-                for ach in &mut cl_ach.achievements {
-                    ach.draw(vec2(ach_x, ach_y));
-                    ach_y += 50.;
+                if windows.is_visible(App::Replay) {
+                    if let Some(current) = &mut playback {
+                        let mut replay_events = Vec::new();
+                        let mut running = true;
+                        for _ in 0..replay_timestep.advance(get_frame_time()) {
+                            running = current.tick(&mut replay_events);
+                            if !running {
+                                break;
+                            }
+                        }
+                        // Only the feedback, the replay must not crash the real computer
+                        // nor unlock anything
+                        events.extend(replay_events.into_iter().filter(|e| {
+                            !matches!(e, Event::Bsod(_) | Event::AchievementUnlocked(_))
+                        }));
+                        replay_view.render(
+                            &current.world,
+                            &resources,
+                            replay_timestep.alpha(),
+                            false,
+                            profile.settings.reduced_flashing,
+                        );
+
+                        if !running {
+                            windows.close(App::Replay);
+                            playback = None;
+                        }
+                    }
+                } else {
+                    replay_timestep.reset();
                 }
 
-                window_decorations(&mut game_state, &mut cross, "Achievements", &mut world);
+                wallpaper.draw();
+                icon_ung.draw();
+                icon_ach.draw();
+                icon_rpl.draw();
+                icon_dbg.draw();
+                icon_set.draw();
+
+                let previous_settings = profile.settings.clone();
+                for window in windows.windows().filter(|w| !w.minimized) {
+                    windows.draw_frame(window, cross.texture);
+                    let content = window.content();
+                    match window.app {
+                        App::Unglitched => game_view.draw(content),
+                        App::Replay => replay_view.draw(content),
+                        App::Achievements => {
+                            desktop::clip(content);
+                            draw_achievements(&world, &resources, &profile, content);
+                            desktop::unclip();
+                        }
+                        App::Debugger => {
+                            let interactive =
+                                !dialogs.is_shown() && windows.focused() == Some(App::Debugger);
+                            desktop::clip(content);
+                            debugger.draw(&world, content, interactive);
+                            desktop::unclip();
+                        }
+                        App::Settings => {
+                            let interactive =
+                                !dialogs.is_shown() && windows.focused() == Some(App::Settings);
+                            desktop::clip(content);
+                            let changed = settings_app.draw(
+                                &mut profile.settings,
+                                &mut difficulty,
+                                content,
+                                interactive,
+                            );
+                            if changed {
+                                difficulty.name().clone_into(&mut profile.difficulty);
+                            }
+                            settings_unsaved |= changed;
+                            desktop::unclip();
+                        }
+                    }
+                    WindowManager::draw_grip(window);
+                }
+                apply_settings(&previous_settings, &profile.settings, &resources);
+                if settings_unsaved && !is_mouse_button_down(MouseButton::Left) {
+                    profile.save();
+                    settings_unsaved = false;
+                }
+                windows.draw_taskbar();
+                tray.draw();
             }
 
             GameState::BSOD => {
+                windows.close_all();
+                playback = None;
+
                 draw_rectangle(0., 0., screen_width(), screen_height(), DARKBLUE);
 
                 draw_bsod_text(&bsod_message);
 
-                if is_key_pressed(KeyCode::Enter) && !world.show_final_bsod {
+                if is_key_pressed(KeyCode::Enter) && !final_bsod {
                     game_state = GameState::Desktop;
 
-                    if world.show_credits {
-                        world.show_credits_1 = true;
+                    dialogs.trigger(Trigger::FirstBsod, &mut events);
+                    if world.achievements.all_unlocked() {
+                        dialogs.trigger(Trigger::AllBugsFound, &mut events);
                     }
                 }
             }
         }
 
-        popup.visible = true;
-
-        if world.show_tutorial_1 {
-            popup.text = "Welcome to Dinwows, the best Operating System";
-            popup.style = PopupStyle::INFO;
-        } else if world.show_tutorial_2 {
-            popup.text = "Play our best game \"Unglitched\" !";
-            popup.style = PopupStyle::WARNING;
-        } else if world.show_tutorial_3 {
-            popup.text = "MOVE with W A S D";
-            popup.style = PopupStyle::WARNING;
-        } else if world.show_tutorial_4 {
-            popup.text = "Use special ability with [SPACE]";
-            popup.style = PopupStyle::ERROR;
-        } else if world.show_tutorial_5 {
-            popup.text = "Use headphones for a better experience";
-            popup.style = PopupStyle::INFO;
-        } else if world.show_tutorial_2_1 {
-            if game_state == GameState::BSOD {
-                popup.visible = false;
+        popup.visible = false;
+        if let Some(step) = dialogs.current() {
+            popup.visible = true;
+            popup.text = profile.settings.bindings.fill_in(&step.text);
+            popup.style = step.style.clone();
+        }
+
+        if popup.draw() {
+            match dialogs.close_current(&mut events) {
+                Some(DialogAction::Bsod(message)) => world.bsod(&message, &mut events),
+                Some(DialogAction::FinalBsod(message)) => {
+                    final_bsod = true;
+                    world.bsod(&message, &mut events);
+                }
+                None => (),
             }
+        }
 
-            popup.text = "This game doesn't have any bugs !";
-            popup.style = PopupStyle::WARNING;
-        } else if world.show_tutorial_2_2 {
-            popup.text = "Try to find all bugs anyway !";
-            popup.style = PopupStyle::ERROR;
-        } else if world.show_input_popup {
-            popup.text = "Enter your name (8 char max)";
-            popup.style = PopupStyle::INFO;
-        } else if world.show_tutorial_2_3 {
-            popup.text = "You can see the bugs found in Achievements";
-            popup.style = PopupStyle::WARNING;
-        } else if world.show_tutorial_2_4 {
-            popup.text = "Once found, bugs will not crash the computer";
-            popup.style = PopupStyle::INFO;
-        } else if world.show_tutorial_2_5 {
-            popup.text = "You can use them to your advantage";
-            popup.style = PopupStyle::INFO;
-        } else if world.show_tutorial_2_6 {
-            popup.text = "But, they WILL cause instability !";
-            popup.style = PopupStyle::WARNING;
-        } else if world.show_credits_1 {
-            popup.text = "Congratulations, you found all the bugs!";
-            popup.style = PopupStyle::INFO;
-        } else if world.show_credits_2 {
-            popup.text = "Or did you ?";
-            popup.style = PopupStyle::WARNING;
-        } else {
-            popup.visible = false;
+        console.draw();
+
+        if dialogs.seen.len() != profile.seen_dialogs.len() {
+            profile.seen_dialogs = dialogs.seen.iter().map(|t| t.name().to_owned()).collect();
+            profile.save();
         }
 
-        popup.draw(&mut world, &mut game_state, &resources);
+        handle_events(
+            &mut events,
+            &resources,
+            &mut game_state,
+            &mut bsod_message,
+            &mut glitch_effect,
+            &mut profile,
+        );
 
-        world.glitch_effect.run();
+        glitch_effect.run(&profile.settings);
 
-        if game_state == GameState::Game && last_game_state != GameState::Game {
+        let game_open = windows.is_open(App::Unglitched);
+        if game_open && !game_was_open {
+            timestep.reset();
             play_sound(
                 resources.music,
                 PlaySoundParams {
                     looped: true,
-                    volume: 0.1,
+                    volume: MUSIC_VOLUME * profile.settings.music_volume(),
                 },
             );
-        } else if game_state != GameState::Game && last_game_state == GameState::Game {
+        } else if !game_open && game_was_open {
             stop_sound(resources.music);
+            if recording.tainted {
+                info!("Not saving the replay of a cheated run");
+            } else {
+                recording.save();
+            }
+            profile.save();
         }
+        game_was_open = game_open;
 
-        last_game_state = game_state.clone();
+        if final_bsod {
+            glitch_effect.set(20, 0.5);
+        }
 
-        if world.show_final_bsod {
-            world.glitch_effect.set(20, 0.5);
-            // game_state = GameState::BSOD;
-            bsod_message = "Thanks for playing".to_string();
+        // Only once the game window closed, so the run is saved
+        if shut_down {
+            if game_was_open {
+                windows.close_all();
+            } else {
+                break;
+            }
         }
 
         next_frame().await;
@@ -0,0 +1,212 @@
+// Smooth paths followed by pathers. Control points are joined with a
+// Catmull-Rom spline, sampled into a polyline with the distance travelled at
+// each sample so movement along it runs at constant speed.
+
+use std::f32::consts::TAU;
+
+use macroquad::math::Vec2;
+
+use crate::{
+    entities::{random_outside_pos, CENTER, WORLD_HEIGHT, WORLD_WIDTH},
+    rng::Rng,
+};
+
+const SAMPLES_PER_SEGMENT: usize = 16;
+/// Distance kept from the walls by `PathFamily::WallHug`
+const WALL_MARGIN: f32 = 1.5;
+
+#[derive(Clone, Debug)]
+pub struct SplinePath {
+    samples: Vec<Vec2>,
+    /// Distance from the start to each sample
+    distances: Vec<f32>,
+}
+
+/// Point `t` (0 to 1) of the Catmull-Rom segment between `p1` and `p2`.
+fn catmull_rom(p0: Vec2, p1: Vec2, p2: Vec2, p3: Vec2, t: f32) -> Vec2 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    0.5 * (2. * p1
+        + (p2 - p0) * t
+        + (2. * p0 - 5. * p1 + 4. * p2 - p3) * t2
+        + (3. * p1 - p0 - 3. * p2 + p3) * t3)
+}
+
+impl SplinePath {
+    /// Smooth curve going through every control point in order.
+    pub fn through(points: &[Vec2]) -> Self {
+        let mut samples = Vec::with_capacity(points.len() * SAMPLES_PER_SEGMENT + 1);
+        for (i, window) in points.windows(2).enumerate() {
+            let (p1, p2) = (window[0], window[1]);
+            // The ends have no neighbor, they are mirrored instead
+            let p0 = if i > 0 { points[i - 1] } else { 2. * p1 - p2 };
+            let p3 = points.get(i + 2).copied().unwrap_or(2. * p2 - p1);
+
+            for s in 0..SAMPLES_PER_SEGMENT {
+                let t = s as f32 / SAMPLES_PER_SEGMENT as f32;
+                samples.push(catmull_rom(p0, p1, p2, p3, t));
+            }
+        }
+        samples.extend(points.last());
+
+        let mut distances = Vec::with_capacity(samples.len());
+        let mut total = 0.;
+        for (i, sample) in samples.iter().enumerate() {
+            if i > 0 {
+                total += (*sample - samples[i - 1]).length();
+            }
+            distances.push(total);
+        }
+
+        Self { samples, distances }
+    }
+
+    pub fn length(&self) -> f32 {
+        self.distances.last().copied().unwrap_or(0.)
+    }
+
+    pub fn start(&self) -> Vec2 {
+        self.samples.first().copied().unwrap_or(CENTER)
+    }
+
+    /// The point `distance` along the path, clamped to its ends.
+    pub fn point_at(&self, distance: f32) -> Vec2 {
+        let next = self.distances.partition_point(|&d| d < distance);
+        if next == 0 {
+            return self.start();
+        }
+        let Some(&end) = self.samples.get(next) else {
+            return self.samples[next - 1];
+        };
+
+        let start = self.samples[next - 1];
+        let span = self.distances[next] - self.distances[next - 1];
+        if span <= 0. {
+            return end;
+        }
+        start.lerp(end, (distance - self.distances[next - 1]) / span)
+    }
+
+    /// Distance of the first point after `from` inside the `min`-`max`
+    /// rectangle.
+    pub fn entry(&self, from: f32, min: Vec2, max: Vec2) -> Option<f32> {
+        let first = self.distances.partition_point(|&d| d < from);
+        self.samples[first..]
+            .iter()
+            .position(|p| p.cmpge(min).all() && p.cmple(max).all())
+            .map(|i| self.distances[first + i])
+    }
+
+    /// The samples between `from` and `from + length`, for previews.
+    pub fn section(&self, from: f32, length: f32) -> impl Iterator<Item = Vec2> + '_ {
+        let first = self.distances.partition_point(|&d| d < from);
+        let last = self.distances.partition_point(|&d| d <= from + length);
+        std::iter::once(self.point_at(from)).chain(self.samples[first..last].iter().copied())
+    }
+}
+
+/// Shapes of pather paths, all entering and leaving through the spawn ring.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PathFamily {
+    /// One figure-eight loop around the middle of the arena
+    FigureEight,
+    /// Back and forth across the arena while moving to the other side
+    ZigZag,
+    /// Circles the target closer and closer, then leaves
+    SpiralIn,
+    /// Once around the arena along the walls
+    WallHug,
+}
+
+impl PathFamily {
+    pub const ALL: [Self; 4] = [
+        Self::FigureEight,
+        Self::ZigZag,
+        Self::SpiralIn,
+        Self::WallHug,
+    ];
+
+    /// A random path of this family from `start`, `target_pos` being the
+    /// player.
+    pub fn path(self, start: Vec2, target_pos: Vec2, rng: &mut Rng) -> SplinePath {
+        let mut points = vec![start];
+        match self {
+            Self::FigureEight => {
+                let size = Vec2::new(rng.gen_range(10., 16.), rng.gen_range(6., 10.));
+                let phase = *rng.choose(&[0., TAU / 2.]).unwrap();
+                points.extend((0..=12).map(|i| {
+                    let t = phase + TAU * i as f32 / 12.;
+                    CENTER + Vec2::new(t.sin(), t.sin() * t.cos() * 2.) * size
+                }));
+            }
+            Self::ZigZag => {
+                let zigs = 6;
+                let from_left = rng.gen_range(0., 1.) < 0.5;
+                points.extend((0..=zigs).map(|i| {
+                    let x = -2. + (WORLD_WIDTH + 4.) * i as f32 / zigs as f32;
+                    let y = if i % 2 == 0 { 3. } else { WORLD_HEIGHT - 3. };
+                    Vec2::new(if from_left { x } else { WORLD_WIDTH - x }, y)
+                }));
+            }
+            Self::SpiralIn => {
+                let start = rng.gen_range(0., TAU);
+                let turn = *rng.choose(&[-1., 1.]).unwrap();
+                let steps = 16;
+                points.extend((0..=steps).map(|i| {
+                    let progress = i as f32 / steps as f32;
+                    let angle = start + turn * progress * 2. * TAU;
+                    target_pos + Vec2::from_angle(angle) * (14. - 12. * progress)
+                }));
+            }
+            Self::WallHug => {
+                let (min, max) = (
+                    Vec2::splat(WALL_MARGIN),
+                    Vec2::new(WORLD_WIDTH, WORLD_HEIGHT) - WALL_MARGIN,
+                );
+                let mut corners = vec![min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)];
+                if rng.gen_range(0., 1.) < 0.5 {
+                    corners.reverse();
+                }
+                corners.rotate_left(rng.gen_range(0, 4) as usize);
+                let first = corners[0];
+                points.extend(corners);
+                points.push(first);
+            }
+        }
+        points.push(random_outside_pos(rng));
+
+        SplinePath::through(&points)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::SPAWN_DIST;
+
+    #[test]
+    fn point_at_is_clamped_to_the_ends() {
+        let path = SplinePath::through(&[Vec2::ZERO, Vec2::new(5., 0.), Vec2::new(10., 0.)]);
+
+        assert!((path.length() - 10.).abs() < 1e-3);
+        assert_eq!(path.point_at(-1.), Vec2::ZERO);
+        assert_eq!(path.point_at(path.length() + 5.), Vec2::new(10., 0.));
+        assert!(path.point_at(2.5).distance(Vec2::new(2.5, 0.)) < 1e-3);
+    }
+
+    #[test]
+    fn paths_go_from_their_start_to_the_spawn_ring() {
+        let mut rng = Rng::new(7);
+        let start = CENTER + Vec2::new(SPAWN_DIST, 0.);
+        for family in PathFamily::ALL {
+            let path = family.path(start, CENTER, &mut rng);
+
+            assert_eq!(path.start(), start, "{family:?}");
+            let end = path.point_at(path.length());
+            assert!(
+                (end.distance(CENTER) - SPAWN_DIST).abs() < 1e-3,
+                "{family:?} ends at {end}"
+            );
+        }
+    }
+}
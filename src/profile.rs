@@ -0,0 +1,200 @@
+use nanoserde::{DeJson, SerJson};
+
+use crate::{achievements::Achievements, settings::Settings, storage};
+
+/// Bump when the format changes, and teach `Profile::migrate` how to upgrade
+/// the previous version.
+pub const PROFILE_VERSION: u32 = 2;
+const PROFILE_KEY: &str = "profile";
+
+#[derive(Clone, SerJson, DeJson)]
+pub struct UnlockedAchievement {
+    /// `Achievement::id`
+    pub id: String,
+    /// Seconds since the Unix epoch
+    pub timestamp: f64,
+}
+
+/// What survives a reload: the bugs found, the player name, best time and
+/// settings.
+#[derive(Clone, SerJson, DeJson)]
+pub struct Profile {
+    pub version: u32,
+    #[nserde(default)]
+    pub player_name: String,
+    #[nserde(default)]
+    pub best_time: f32,
+    #[nserde(default)]
+    pub unlocked: Vec<UnlockedAchievement>,
+    /// Dialog triggers already played, by `Trigger::name`
+    #[nserde(default)]
+    pub seen_dialogs: Vec<String>,
+    /// Picked before the last run, by `Difficulty::name`
+    #[nserde(default)]
+    pub difficulty: String,
+    #[nserde(default)]
+    pub settings: Settings,
+}
+
+impl Profile {
+    pub fn new() -> Self {
+        Self {
+            version: PROFILE_VERSION,
+            player_name: String::new(),
+            best_time: 0.,
+            unlocked: Vec::new(),
+            seen_dialogs: Vec::new(),
+            difficulty: String::new(),
+            settings: Settings::default(),
+        }
+    }
+
+    pub fn load() -> Self {
+        storage::load(PROFILE_KEY)
+            .and_then(|json| Self::from_json(&json))
+            .unwrap_or_else(Self::new)
+    }
+
+    pub fn from_json(json: &str) -> Option<Self> {
+        match VersionOnly::deserialize_json(json) {
+            Ok(header) => Self::migrate(header.version, json),
+            Err(err) => {
+                macroquad::logging::warn!("Ignoring corrupted profile: {}", err);
+                None
+            }
+        }
+    }
+
+    fn migrate(version: u32, json: &str) -> Option<Self> {
+        let result = match version {
+            1 => ProfileV1::deserialize_json(json).map(ProfileV1::upgrade),
+            PROFILE_VERSION => Self::deserialize_json(json),
+            version => {
+                macroquad::logging::warn!("Ignoring profile with unknown version {}", version);
+                return None;
+            }
+        };
+        result
+            .map_err(|err| macroquad::logging::warn!("Ignoring corrupted profile: {}", err))
+            .ok()
+    }
+
+    pub fn save(&self) {
+        storage::save(PROFILE_KEY, &self.serialize_json());
+    }
+
+    pub fn is_unlocked(&self, id: &str) -> bool {
+        self.unlocked.iter().any(|u| u.id == id)
+    }
+
+    pub fn unlock(&mut self, id: &str, timestamp: f64) {
+        if !self.is_unlocked(id) {
+            self.unlocked.push(UnlockedAchievement {
+                id: id.to_owned(),
+                timestamp,
+            });
+        }
+    }
+
+    /// Restores the bugs found in previous sessions.
+    pub fn apply(&self, achievements: &mut Achievements) {
+        for achievement in &mut achievements.achievements {
+            achievement.unlocked = self.is_unlocked(&achievement.id);
+        }
+    }
+}
+
+/// Read first to pick the right struct, unknown fields are ignored.
+#[derive(DeJson)]
+struct VersionOnly {
+    version: u32,
+}
+
+#[derive(DeJson)]
+struct UnlockedAchievementV1 {
+    name: String,
+    timestamp: f64,
+}
+
+/// Achievements were stored by display name.
+#[derive(DeJson)]
+struct ProfileV1 {
+    #[nserde(default)]
+    player_name: String,
+    #[nserde(default)]
+    best_time: f32,
+    #[nserde(default)]
+    unlocked: Vec<UnlockedAchievementV1>,
+    #[nserde(default)]
+    seen_dialogs: Vec<String>,
+}
+
+impl ProfileV1 {
+    /// The names of that version are the ids of `assets/achievements.ron`.
+    fn upgrade(self) -> Profile {
+        Profile {
+            version: PROFILE_VERSION,
+            player_name: self.player_name,
+            best_time: self.best_time,
+            unlocked: self
+                .unlocked
+                .into_iter()
+                .map(|u| UnlockedAchievement {
+                    id: u.name,
+                    timestamp: u.timestamp,
+                })
+                .collect(),
+            seen_dialogs: self.seen_dialogs,
+            difficulty: String::new(),
+            settings: Settings::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upgrades_v1_names_to_ids() {
+        let json = r#"{
+            "version": 1,
+            "player_name": "ada",
+            "best_time": 12.5,
+            "unlocked": [{"name": "second_chance", "timestamp": 3.0}],
+            "seen_dialogs": ["first_launch"]
+        }"#;
+        let profile = Profile::from_json(json).unwrap();
+
+        assert_eq!(profile.version, PROFILE_VERSION);
+        assert_eq!(profile.player_name, "ada");
+        assert!((profile.best_time - 12.5).abs() < f32::EPSILON);
+        assert!(profile.is_unlocked("second_chance"));
+        assert_eq!(profile.seen_dialogs, ["first_launch"]);
+        assert_eq!(profile.settings, Settings::default());
+    }
+
+    #[test]
+    fn current_version_round_trips() {
+        let mut profile = Profile::new();
+        profile.player_name = "bob".to_owned();
+        profile.unlock("over_9000", 1.);
+        profile.settings.reduced_flashing = true;
+
+        let loaded = Profile::from_json(&profile.serialize_json()).unwrap();
+        let mut achievements = Achievements::new();
+        loaded.apply(&mut achievements);
+
+        assert_eq!(loaded.player_name, "bob");
+        assert!(achievements.get("over_9000").unwrap().unlocked);
+        assert!(!achievements.get("second_chance").unwrap().unlocked);
+        assert_eq!(loaded.settings, profile.settings);
+    }
+
+    #[test]
+    fn ignores_unknown_and_corrupted_profiles() {
+        assert!(Profile::from_json(r#"{"version": 99}"#).is_none());
+        assert!(Profile::from_json("{").is_none());
+        assert!(Profile::from_json(r#"{"version": 1, "unlocked": 3}"#).is_none());
+    }
+}
@@ -0,0 +1,310 @@
+use std::fmt::Write;
+
+use nanoserde::{DeJson, SerJson};
+
+use crate::{
+    archetypes::Archetypes,
+    director::{Difficulty, WaveScript},
+    events::Event,
+    input::Input,
+    storage,
+    world::World,
+};
+
+const MAGIC: &[u8; 4] = b"UNGR";
+const VERSION: u8 = 4;
+/// Magic, version, seed, data hash, instability, difficulty and the number
+/// of unlocked ids.
+const HEADER_LEN: usize = 27;
+const REPLAY_KEY: &str = "replay";
+
+/// Everything needed to replay a run frame by frame: the starting state that
+/// isn't derived from the seed, and one `Input` per tick.
+#[derive(Clone, Default)]
+pub struct Replay {
+    pub seed: u64,
+    /// `World::data_hash` of the recorded run
+    pub data_hash: u64,
+    /// Ids of the bugs already found, they decide whether a bug crashes the
+    /// computer or only raises the instability.
+    pub unlocked: Vec<String>,
+    pub instability: u32,
+    pub difficulty: Difficulty,
+    pub inputs: Vec<Input>,
+    /// Changed from the console while recording, the inputs no longer replay
+    /// the run so it isn't saved
+    pub tainted: bool,
+}
+
+impl Replay {
+    pub fn new(world: &World) -> Self {
+        let unlocked = world
+            .achievements
+            .achievements
+            .iter()
+            .filter(|a| a.unlocked)
+            .map(|a| a.id.clone())
+            .collect();
+
+        Self {
+            seed: world.seed,
+            data_hash: world.data_hash(),
+            unlocked,
+            instability: world.instability,
+            difficulty: world.director.difficulty,
+            inputs: Vec::new(),
+            // Godmode survives restarts
+            tainted: world.godmode,
+        }
+    }
+
+    pub fn record(&mut self, input: Input) {
+        self.inputs.push(input);
+    }
+
+    /// `World` in the same state as when the recording started, `None` if
+    /// it was recorded with other data files.
+    pub fn world(&self, archetypes: Archetypes, script: WaveScript) -> Option<World> {
+        let mut world = World::with_seed(self.seed, archetypes, script);
+        if world.data_hash() != self.data_hash {
+            return None;
+        }
+        for id in &self.unlocked {
+            world.achievements.unlock(id);
+        }
+        world.instability = self.instability;
+        world.director.difficulty = self.difficulty;
+        world.has_game_started = true;
+        Some(world)
+    }
+
+    /// Header, then the unlocked ids as length-prefixed strings, followed by
+    /// run-length encoded inputs as `(bits, count)` pairs.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + self.inputs.len() / 4);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.extend_from_slice(&self.data_hash.to_le_bytes());
+        bytes.extend_from_slice(&self.instability.to_le_bytes());
+        bytes.push(self.difficulty as u8);
+
+        let ids: Vec<&[u8]> = self
+            .unlocked
+            .iter()
+            .map(String::as_bytes)
+            .filter(|id| u8::try_from(id.len()).is_ok())
+            .take(u8::MAX as usize)
+            .collect();
+        bytes.push(ids.len() as u8);
+        for id in ids {
+            bytes.push(id.len() as u8);
+            bytes.extend_from_slice(id);
+        }
+
+        let mut inputs = self.inputs.iter().map(|i| i.to_bits()).peekable();
+        while let Some(bits) = inputs.next() {
+            let mut count = 1u8;
+            while count < u8::MAX && inputs.next_if_eq(&bits).is_some() {
+                count += 1;
+            }
+            bytes.push(bits);
+            bytes.push(count);
+        }
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let (header, mut body) = bytes.split_at_checked(HEADER_LEN)?;
+        if &header[0..4] != MAGIC || header[4] != VERSION {
+            return None;
+        }
+
+        let mut unlocked = Vec::new();
+        for _ in 0..header[26] {
+            let (&len, rest) = body.split_first()?;
+            let (id, rest) = rest.split_at_checked(len as usize)?;
+            unlocked.push(String::from_utf8(id.to_vec()).ok()?);
+            body = rest;
+        }
+        if body.len() % 2 != 0 {
+            return None;
+        }
+
+        let mut inputs = Vec::new();
+        for pair in body.chunks_exact(2) {
+            let input = Input::from_bits(pair[0]);
+            inputs.extend(std::iter::repeat_n(input, pair[1] as usize));
+        }
+
+        Some(Self {
+            seed: u64::from_le_bytes(header[5..13].try_into().ok()?),
+            data_hash: u64::from_le_bytes(header[13..21].try_into().ok()?),
+            unlocked,
+            instability: u32::from_le_bytes(header[21..25].try_into().ok()?),
+            difficulty: *Difficulty::ALL.get(header[25] as usize)?,
+            inputs,
+            tainted: false,
+        })
+    }
+
+    /// Kept as a string of hex digits, the storage only holds text.
+    pub fn save(&self) {
+        storage::save(REPLAY_KEY, &to_hex(&self.to_bytes()).serialize_json());
+    }
+
+    pub fn load() -> Option<Self> {
+        let hex = String::deserialize_json(&storage::load(REPLAY_KEY)?).ok()?;
+        Self::from_bytes(&from_hex(&hex)?)
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        let _ = write!(hex, "{byte:02x}");
+    }
+    hex
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Feeds a `Replay` back into its own `World`.
+pub struct Playback {
+    pub replay: Replay,
+    pub world: World,
+    pub frame: usize,
+}
+
+impl Playback {
+    /// `None` if the replay doesn't match the data files, see
+    /// `Replay::world`.
+    pub fn new(replay: Replay, archetypes: Archetypes, script: WaveScript) -> Option<Self> {
+        Some(Self {
+            world: replay.world(archetypes, script)?,
+            replay,
+            frame: 0,
+        })
+    }
+
+    /// Returns `false` once the recording is over.
+    pub fn tick(&mut self, events: &mut Vec<Event>) -> bool {
+        let Some(input) = self.replay.inputs.get(self.frame) else {
+            return false;
+        };
+        self.frame += 1;
+
+        let first_event = events.len();
+        self.world.tick(input, events);
+
+        !events[first_event..]
+            .iter()
+            .any(|e| matches!(e, Event::Bsod(_)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{archetypes, script};
+
+    fn data() -> (Archetypes, WaveScript) {
+        let archetypes = archetypes();
+        let script = script(&archetypes);
+        (archetypes, script)
+    }
+
+    fn replay() -> Replay {
+        let (archetypes, script) = data();
+        let mut world = World::with_seed(42, archetypes, script);
+        world.achievements.unlock("second_chance");
+        world.instability = 3;
+        world.director.difficulty = Difficulty::Hard;
+
+        let mut replay = Replay::new(&world);
+        // Long enough runs to need several `(bits, count)` pairs
+        for (bits, count) in [(0, 300), (0b1001, 1), (0b0010, 700), (0, 2)] {
+            for _ in 0..count {
+                replay.record(Input::from_bits(bits));
+            }
+        }
+        replay
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let replay = replay();
+        let bytes = replay.to_bytes();
+        let loaded = Replay::from_bytes(&bytes).unwrap();
+
+        // 2 + 1 + 3 + 1 pairs after the header and the unlocked id
+        assert_eq!(bytes.len(), HEADER_LEN + 1 + "second_chance".len() + 7 * 2);
+        assert_eq!(loaded.seed, 42);
+        assert_eq!(loaded.data_hash, replay.data_hash);
+        assert_eq!(loaded.unlocked, ["second_chance"]);
+        assert_eq!(loaded.instability, 3);
+        assert_eq!(loaded.difficulty, Difficulty::Hard);
+        assert!(loaded.inputs == replay.inputs);
+    }
+
+    #[test]
+    fn stored_as_hex() {
+        let bytes = replay().to_bytes();
+
+        assert_eq!(to_hex(&[0, 0x5a, 0xff]), "005aff");
+        assert_eq!(from_hex(&to_hex(&bytes)).unwrap(), bytes);
+        assert!(from_hex("5a0").is_none());
+        assert!(from_hex("zz").is_none());
+    }
+
+    #[test]
+    fn rejects_other_formats() {
+        let bytes = replay().to_bytes();
+
+        let mut old = bytes.clone();
+        old[4] = VERSION - 1;
+        assert!(Replay::from_bytes(&old).is_none());
+        assert!(Replay::from_bytes(&bytes[..HEADER_LEN - 1]).is_none());
+        // An odd byte left over isn't a `(bits, count)` pair
+        assert!(Replay::from_bytes(&bytes[..bytes.len() - 1]).is_none());
+    }
+
+    #[test]
+    fn only_plays_with_the_same_data() {
+        let replay = replay();
+        let (archetypes, script) = data();
+
+        let world = replay.world(archetypes.clone(), script.clone()).unwrap();
+        assert!(world.achievements.get("second_chance").unwrap().unlocked);
+        assert_eq!(world.instability, 3);
+
+        let mut other = script;
+        other.hash ^= 1;
+        assert!(replay.world(archetypes, other).is_none());
+    }
+
+    #[test]
+    fn playback_ends_with_the_recording() {
+        let (archetypes, script) = data();
+        let mut replay = Replay::new(&World::with_seed(1, archetypes.clone(), script.clone()));
+        for _ in 0..120 {
+            replay.record(Input::default());
+        }
+        let mut playback = Playback::new(replay, archetypes, script).unwrap();
+        let mut events = Vec::new();
+
+        let mut ticks = 0;
+        while playback.tick(&mut events) {
+            ticks += 1;
+        }
+
+        assert_eq!(ticks, 120);
+        assert!(!playback.tick(&mut events));
+    }
+}
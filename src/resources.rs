@@ -1,4 +1,12 @@
 use macroquad::audio::{load_sound_from_bytes, Sound};
+
+use std::collections::HashMap;
+
+use crate::{
+    achievements::{Achievement, Achievements},
+    archetypes::Archetypes,
+    events::{ItemKind, Sfx},
+};
 use macroquad::prelude::*;
 
 pub struct Resources {
@@ -6,17 +14,22 @@ pub struct Resources {
     pub player_hit: Texture2D,
     pub power_up: Texture2D,
 
-    pub bullet: Texture2D,
-    pub follower: Texture2D,
-    pub pather: Texture2D,
-
-    pub bullet_glitch: Texture2D,
-    pub follower_glitch: Texture2D,
-    pub pather_glitch: Texture2D,
+    /// Normal and glitched clone sprites, by archetype
+    pub enemies: Vec<(Texture2D, Texture2D)>,
+    pub kernel: Texture2D,
 
     pub heart: Texture2D,
     pub energy: Texture2D,
+    /// By `ItemKind`, in `ItemKind::ALL` order
+    pub item_sprites: [Texture2D; ItemKind::ALL.len()],
+    pub item_sounds: [Sound; ItemKind::ALL.len()],
     pub bug: Texture2D,
+    /// Arrow pointing right, tinted by the frontend
+    pub indicator: Texture2D,
+
+    /// By achievement id
+    pub achievement_icons: HashMap<String, Texture2D>,
+    pub unknown_achievement: Texture2D,
 
     pub glitch_sound: Sound,
     pub bsod_sound: Sound,
@@ -30,6 +43,33 @@ pub struct Resources {
     pub or_did_you: Sound,
 }
 
+/// Images that data files (e.g. `assets/achievements.ron`) can refer to by
+/// file name. They are embedded so the web build stays a single file.
+pub fn embedded_image(name: &str) -> Option<&'static [u8]> {
+    Some(match name {
+        "bullet.png" => include_bytes!("../assets/images/bullet.png"),
+        "bullet_glitch.png" => include_bytes!("../assets/images/bullet_glitch.png"),
+        "follower.png" => include_bytes!("../assets/images/follower.png"),
+        "follower_glitch.png" => include_bytes!("../assets/images/follower_glitch.png"),
+        "pather.png" => include_bytes!("../assets/images/pather.png"),
+        "pather_glitch.png" => include_bytes!("../assets/images/pather_glitch.png"),
+        "splitter.png" => include_bytes!("../assets/images/splitter.png"),
+        "splitter_glitch.png" => include_bytes!("../assets/images/splitter_glitch.png"),
+        "orbiter.png" => include_bytes!("../assets/images/orbiter.png"),
+        "orbiter_glitch.png" => include_bytes!("../assets/images/orbiter_glitch.png"),
+        "name_overflow.png" => include_bytes!("../assets/images/name_overflow.png"),
+        "unstable.png" => include_bytes!("../assets/images/unstable.png"),
+        "second_chance.png" => include_bytes!("../assets/images/second_chance.png"),
+        "over_healed.png" => include_bytes!("../assets/images/over_healed.png"),
+        "unlimited_power.png" => include_bytes!("../assets/images/unlimited_power.png"),
+        "over_9000.png" => include_bytes!("../assets/images/over_9000.png"),
+        "up.png" => include_bytes!("../assets/images/up.png"),
+        "kernel_panic.png" => include_bytes!("../assets/images/kernel_panic.png"),
+        "ach_unknown.png" => include_bytes!("../assets/images/ach_unknown.png"),
+        _ => return None,
+    })
+}
+
 fn new_texture(bytes: &[u8]) -> Texture2D {
     let texture = Texture2D::from_file_with_format(bytes, None);
     texture.set_filter(FilterMode::Nearest);
@@ -38,22 +78,54 @@ fn new_texture(bytes: &[u8]) -> Texture2D {
 }
 
 impl Resources {
-    pub async fn load() -> Self {
+    pub async fn load(achievements: &Achievements, archetypes: &Archetypes) -> Self {
         let player = new_texture(include_bytes!("../assets/images/player.png"));
         let player_hit = new_texture(include_bytes!("../assets/images/player_hit.png"));
         let power_up = new_texture(include_bytes!("../assets/images/power_up.png"));
 
-        let bullet = new_texture(include_bytes!("../assets/images/bullet.png"));
-        let follower = new_texture(include_bytes!("../assets/images/follower.png"));
-        let pather = new_texture(include_bytes!("../assets/images/pather.png"));
-
-        let bullet_glitch = new_texture(include_bytes!("../assets/images/bullet_glitch.png"));
-        let follower_glitch = new_texture(include_bytes!("../assets/images/follower_glitch.png"));
-        let pather_glitch = new_texture(include_bytes!("../assets/images/pather_glitch.png"));
+        // Image names are checked by `Archetypes::from_ron`
+        let mut textures: HashMap<&str, Texture2D> = HashMap::new();
+        let mut texture = |name| {
+            *textures
+                .entry(name)
+                .or_insert_with(|| new_texture(embedded_image(name).unwrap()))
+        };
+        let enemies = archetypes
+            .archetypes
+            .iter()
+            .map(|a| (texture(&a.texture), texture(&a.glitch_texture)))
+            .collect();
+        let kernel = new_texture(include_bytes!("../assets/images/kernel.png"));
 
         let heart = new_texture(include_bytes!("../assets/images/heart.png"));
         let energy = new_texture(include_bytes!("../assets/images/energy.png"));
+        let item_sprites = [
+            heart,
+            energy,
+            new_texture(include_bytes!("../assets/images/score.png")),
+            new_texture(include_bytes!("../assets/images/speed.png")),
+            new_texture(include_bytes!("../assets/images/patch.png")),
+            new_texture(include_bytes!("../assets/images/magnet.png")),
+            new_texture(include_bytes!("../assets/images/shield.png")),
+        ];
         let bug = new_texture(include_bytes!("../assets/images/bug.png"));
+        let indicator = new_texture(include_bytes!("../assets/images/indicator.png"));
+
+        let unknown_achievement = new_texture(include_bytes!("../assets/images/ach_unknown.png"));
+        let achievement_icons = achievements
+            .achievements
+            .iter()
+            .map(|a| {
+                let texture = embedded_image(&a.icon).map_or_else(
+                    || {
+                        warn!("Missing icon {} for achievement {}", a.icon, a.id);
+                        unknown_achievement
+                    },
+                    new_texture,
+                );
+                (a.id.clone(), texture)
+            })
+            .collect();
 
         let bsod_sound = load_sound_from_bytes(include_bytes!("../assets/sounds/bsod_sound.wav"))
             .await
@@ -88,6 +160,28 @@ impl Resources {
                 .await
                 .unwrap();
 
+        let item_sounds = [
+            picking_item_sound,
+            load_sound_from_bytes(include_bytes!("../assets/sounds/mana_item_sound.wav"))
+                .await
+                .unwrap(),
+            load_sound_from_bytes(include_bytes!("../assets/sounds/score_item_sound.wav"))
+                .await
+                .unwrap(),
+            load_sound_from_bytes(include_bytes!("../assets/sounds/speed_item_sound.wav"))
+                .await
+                .unwrap(),
+            load_sound_from_bytes(include_bytes!("../assets/sounds/patch_item_sound.wav"))
+                .await
+                .unwrap(),
+            load_sound_from_bytes(include_bytes!("../assets/sounds/magnet_item_sound.wav"))
+                .await
+                .unwrap(),
+            load_sound_from_bytes(include_bytes!("../assets/sounds/shield_item_sound.wav"))
+                .await
+                .unwrap(),
+        ];
+
         let music = load_sound_from_bytes(include_bytes!("../assets/sounds/music.wav"))
             .await
             .unwrap();
@@ -100,24 +194,48 @@ impl Resources {
             player,
             player_hit,
             power_up,
-            bullet,
-            follower,
-            pather,
-            bullet_glitch,
-            follower_glitch,
-            pather_glitch,
+            enemies,
+            kernel,
             heart,
             energy,
+            item_sprites,
+            item_sounds,
             bug,
+            indicator,
+            achievement_icons,
+            unknown_achievement,
+            glitch_sound,
             bsod_sound,
             small_bug_sound,
             explosion_sound,
             explosion_bug_sound,
             hit_sound,
             picking_item_sound,
-            glitch_sound,
             music,
             or_did_you,
         }
     }
+
+    pub fn achievement_icon(&self, achievement: &Achievement) -> Texture2D {
+        if achievement.hidden && !achievement.unlocked {
+            return self.unknown_achievement;
+        }
+        self.achievement_icons
+            .get(&achievement.id)
+            .copied()
+            .unwrap_or(self.unknown_achievement)
+    }
+
+    pub const fn sfx(&self, sfx: Sfx) -> (Sound, f32) {
+        match sfx {
+            Sfx::Hit => (self.hit_sound, 0.5),
+            Sfx::PickItem => (self.picking_item_sound, 0.5),
+            Sfx::Explosion => (self.explosion_sound, 0.5),
+            Sfx::Glitch => (self.glitch_sound, 0.2),
+            Sfx::SmallBug => (self.small_bug_sound, 1.),
+            Sfx::OrDidYou => (self.or_did_you, 0.5),
+            Sfx::ExplosionBug => (self.explosion_bug_sound, 0.5),
+            Sfx::Pickup(kind) => (self.item_sounds[kind as usize], 0.5),
+        }
+    }
 }
@@ -0,0 +1,67 @@
+// Small deterministic PRNG (SplitMix64) so the simulation never touches
+// macroquad's process-wide `rand` state, and a stable hash.
+
+#[derive(Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+pub trait SampleRange: Sized {
+    fn sample(rng: &mut Rng, low: Self, high: Self) -> Self;
+}
+
+impl Rng {
+    pub const fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub const fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform float in `[0, 1)`.
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Same semantics as `macroquad::rand::gen_range`: `high` is exclusive.
+    pub fn gen_range<T: SampleRange>(&mut self, low: T, high: T) -> T {
+        T::sample(self, low, high)
+    }
+
+    /// Uniformly picked element, `None` if `items` is empty.
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            return None;
+        }
+        items.get((self.next_u64() % items.len() as u64) as usize)
+    }
+}
+
+impl SampleRange for f32 {
+    fn sample(rng: &mut Rng, low: Self, high: Self) -> Self {
+        rng.next_f32().mul_add(high - low, low)
+    }
+}
+
+impl SampleRange for i32 {
+    fn sample(rng: &mut Rng, low: Self, high: Self) -> Self {
+        if high <= low {
+            return low;
+        }
+        let span = i64::from(high) - i64::from(low);
+        (i64::from(low) + (rng.next_u64() % span as u64).cast_signed()) as Self
+    }
+}
+
+/// FNV-1a, unlike `std`'s hasher it's the same across runs and builds, so it
+/// can be saved.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xCBF2_9CE4_8422_2325, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01B3)
+    })
+}
@@ -0,0 +1,250 @@
+// Player settings and the Settings desktop app editing them. They're saved in
+// the profile and apply as soon as they change.
+
+use macroquad::prelude::*;
+use nanoserde::{DeJson, SerJson};
+
+use crate::{
+    director::Difficulty,
+    input::{key_name, Bindings, Control},
+};
+
+const FONT_SIZE: f32 = 22.;
+const ROW_HEIGHT: f32 = 36.;
+const PADDING: f32 = 12.;
+const LABEL_WIDTH: f32 = 190.;
+const SLIDER_WIDTH: f32 = 200.;
+const BOX_SIZE: f32 = 20.;
+const KEY_BUTTON_WIDTH: f32 = 110.;
+
+#[derive(Clone, PartialEq, Debug, SerJson, DeJson)]
+pub struct Settings {
+    /// Volumes from 0 to 1, the music and the effects are also scaled by the
+    /// master volume
+    #[nserde(default = 1.0)]
+    pub master_volume: f32,
+    #[nserde(default = 1.0)]
+    pub music_volume: f32,
+    #[nserde(default = 1.0)]
+    pub sfx_volume: f32,
+    #[nserde(default)]
+    pub bindings: Bindings,
+    #[nserde(default)]
+    pub fullscreen: bool,
+    /// Scales the screen shake of glitches, from 0 to 1
+    #[nserde(default = 1.0)]
+    pub glitch_intensity: f32,
+    /// No blinking sprites nor color flicker
+    #[nserde(default)]
+    pub reduced_flashing: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            master_volume: 1.,
+            music_volume: 1.,
+            sfx_volume: 1.,
+            bindings: Bindings::default(),
+            fullscreen: false,
+            glitch_intensity: 1.,
+            reduced_flashing: false,
+        }
+    }
+}
+
+impl Settings {
+    pub fn music_volume(&self) -> f32 {
+        self.master_volume * self.music_volume
+    }
+
+    pub fn sfx_volume(&self) -> f32 {
+        self.master_volume * self.sfx_volume
+    }
+}
+
+/// The Settings app.
+#[derive(Default)]
+pub struct SettingsApp {
+    /// Waiting for the new key of that control
+    rebinding: Option<Control>,
+    /// Slider dragged, by row
+    dragging: Option<usize>,
+}
+
+impl SettingsApp {
+    /// The app content, editing `settings` and `difficulty` in place.
+    /// `interactive` is `false` while another window or a dialog has the
+    /// mouse. Returns `true` if a setting changed.
+    pub fn draw(
+        &mut self,
+        settings: &mut Settings,
+        difficulty: &mut Difficulty,
+        rect: Rect,
+        interactive: bool,
+    ) -> bool {
+        let mouse = Vec2::from(mouse_position());
+        let clicked = interactive && is_mouse_button_pressed(MouseButton::Left);
+        if !is_mouse_button_down(MouseButton::Left) || !interactive {
+            self.dragging = None;
+        }
+        let before = (settings.clone(), *difficulty);
+
+        let x = rect.x + PADDING;
+        let mut y = rect.y + PADDING;
+        let heading = |text: &str, y: &mut f32| {
+            draw_text(text, x, *y + FONT_SIZE, FONT_SIZE + 4., YELLOW);
+            *y += ROW_HEIGHT;
+        };
+
+        heading("Game", &mut y);
+        difficulty_buttons(difficulty, Vec2::new(x, y), clicked, mouse);
+        y += ROW_HEIGHT;
+
+        heading("Audio", &mut y);
+        let sliders = [
+            ("Master volume", &mut settings.master_volume),
+            ("Music volume", &mut settings.music_volume),
+            ("Effects volume", &mut settings.sfx_volume),
+        ];
+        for (i, (label, value)) in sliders.into_iter().enumerate() {
+            self.slider(i, label, value, Vec2::new(x, y), clicked, mouse);
+            y += ROW_HEIGHT;
+        }
+
+        heading("Display", &mut y);
+        self.slider(
+            3,
+            "Glitch intensity",
+            &mut settings.glitch_intensity,
+            Vec2::new(x, y),
+            clicked,
+            mouse,
+        );
+        y += ROW_HEIGHT;
+        for (label, value) in [
+            ("Fullscreen", &mut settings.fullscreen),
+            ("Reduced flashing", &mut settings.reduced_flashing),
+        ] {
+            checkbox(label, value, Vec2::new(x, y), clicked, mouse);
+            y += ROW_HEIGHT;
+        }
+
+        heading("Controls", &mut y);
+        if let Some(control) = self.rebinding {
+            if is_key_pressed(KeyCode::Escape) || clicked {
+                self.rebinding = None;
+            } else if let Some(key) = get_last_key_pressed().filter(|&k| key_name(k).is_some()) {
+                settings.bindings.bind(control, key);
+                self.rebinding = None;
+            }
+        }
+        for control in Control::ALL {
+            draw_text(control.name(), x, y + FONT_SIZE, FONT_SIZE, WHITE);
+            let button = Rect::new(x + LABEL_WIDTH, y + 4., KEY_BUTTON_WIDTH, ROW_HEIGHT - 8.);
+            let waiting = self.rebinding == Some(control);
+            draw_rectangle(
+                button.x,
+                button.y,
+                button.w,
+                button.h,
+                if waiting { YELLOW } else { LIGHTGRAY },
+            );
+            draw_rectangle_lines(button.x, button.y, button.w, button.h, 2., WHITE);
+            let label = if waiting {
+                "press..."
+            } else {
+                key_name(settings.bindings.key(control)).unwrap_or("?")
+            };
+            draw_text(label, button.x + 10., button.y + 20., FONT_SIZE, BLACK);
+            if clicked && !waiting && button.contains(mouse) {
+                self.rebinding = Some(control);
+            }
+            y += ROW_HEIGHT;
+        }
+
+        (settings.clone(), *difficulty) != before
+    }
+
+    /// A horizontal bar from 0 to 1, clicked or dragged to set `value`.
+    fn slider(
+        &mut self,
+        row: usize,
+        label: &str,
+        value: &mut f32,
+        pos: Vec2,
+        clicked: bool,
+        mouse: Vec2,
+    ) {
+        draw_text(label, pos.x, pos.y + FONT_SIZE, FONT_SIZE, WHITE);
+        let bar = Rect::new(
+            pos.x + LABEL_WIDTH,
+            pos.y + 8.,
+            SLIDER_WIDTH,
+            ROW_HEIGHT - 16.,
+        );
+        if clicked && bar.contains(mouse) {
+            self.dragging = Some(row);
+        }
+        if self.dragging == Some(row) {
+            *value = ((mouse.x - bar.x) / bar.w).clamp(0., 1.);
+        }
+
+        draw_rectangle(bar.x, bar.y, bar.w, bar.h, DARKGRAY);
+        draw_rectangle(bar.x, bar.y, bar.w * *value, bar.h, GREEN);
+        draw_rectangle_lines(bar.x, bar.y, bar.w, bar.h, 2., WHITE);
+        draw_text(
+            &format!("{:.0}%", *value * 100.),
+            bar.right() + 10.,
+            pos.y + FONT_SIZE,
+            FONT_SIZE,
+            WHITE,
+        );
+    }
+}
+
+/// The difficulty of the next runs, the current one keeps its own.
+fn difficulty_buttons(difficulty: &mut Difficulty, pos: Vec2, clicked: bool, mouse: Vec2) {
+    draw_text("Difficulty", pos.x, pos.y + FONT_SIZE, FONT_SIZE, WHITE);
+    let mut button = Rect::new(pos.x + LABEL_WIDTH, pos.y + 4., 0., ROW_HEIGHT - 8.);
+    for option in Difficulty::ALL {
+        button.w = measure_text(option.name(), None, FONT_SIZE as u16, 1.).width + 20.;
+        if clicked && button.contains(mouse) {
+            *difficulty = option;
+        }
+        let color = if option == *difficulty {
+            YELLOW
+        } else {
+            LIGHTGRAY
+        };
+        draw_rectangle(button.x, button.y, button.w, button.h, color);
+        draw_rectangle_lines(button.x, button.y, button.w, button.h, 2., WHITE);
+        draw_text(
+            option.name(),
+            button.x + 10.,
+            button.y + 20.,
+            FONT_SIZE,
+            BLACK,
+        );
+        button.x += button.w + 8.;
+    }
+}
+
+fn checkbox(label: &str, value: &mut bool, pos: Vec2, clicked: bool, mouse: Vec2) {
+    draw_text(label, pos.x, pos.y + FONT_SIZE, FONT_SIZE, WHITE);
+    let check = Rect::new(pos.x + LABEL_WIDTH, pos.y + 8., BOX_SIZE, BOX_SIZE);
+    if clicked && check.contains(mouse) {
+        *value = !*value;
+    }
+    draw_rectangle(check.x, check.y, check.w, check.h, DARKGRAY);
+    draw_rectangle_lines(check.x, check.y, check.w, check.h, 2., WHITE);
+    if *value {
+        draw_rectangle(
+            check.x + 5.,
+            check.y + 5.,
+            check.w - 10.,
+            check.h - 10.,
+            GREEN,
+        );
+    }
+}
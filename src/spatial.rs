@@ -0,0 +1,273 @@
+// Uniform grid used by `World` for collision and range queries. Entities are
+// referred to by their index in the slice the grid was built from, so it has
+// to be rebuilt whenever that slice changes (once per tick is enough).
+
+use macroquad::math::Vec2;
+
+pub struct SpatialHash {
+    min: Vec2,
+    cell_size: f32,
+    cols: usize,
+    rows: usize,
+    /// Start of each cell in `entries`, plus one past the last cell
+    starts: Vec<usize>,
+    /// `(index, pos, radius)`, grouped by cell
+    entries: Vec<(usize, Vec2, f32)>,
+    /// Entries with their cell in insertion order, and the next free slot of
+    /// each cell, both only kept to reuse their allocation
+    unsorted: Vec<(usize, (usize, Vec2, f32))>,
+    cursors: Vec<usize>,
+    max_radius: f32,
+}
+
+impl SpatialHash {
+    /// Covers `min` to `max`, anything outside is kept in the border cells.
+    pub fn new(min: Vec2, max: Vec2, cell_size: f32) -> Self {
+        let size = max - min;
+        let cols = (size.x / cell_size).ceil().max(1.) as usize;
+        let rows = (size.y / cell_size).ceil().max(1.) as usize;
+
+        Self {
+            min,
+            cell_size,
+            cols,
+            rows,
+            starts: vec![0; cols * rows + 1],
+            entries: Vec::new(),
+            unsorted: Vec::new(),
+            cursors: Vec::new(),
+            max_radius: 0.,
+        }
+    }
+
+    fn cell_coords(&self, pos: Vec2) -> (usize, usize) {
+        let cell = ((pos - self.min) / self.cell_size).floor();
+        (
+            (cell.x.max(0.) as usize).min(self.cols - 1),
+            (cell.y.max(0.) as usize).min(self.rows - 1),
+        )
+    }
+
+    /// Replaces the content with `(pos, radius)` of each entity.
+    pub fn rebuild(&mut self, entities: impl Iterator<Item = (Vec2, f32)>) {
+        self.unsorted.clear();
+        self.starts.fill(0);
+        self.max_radius = 0.;
+
+        for (i, (pos, radius)) in entities.enumerate() {
+            let (x, y) = self.cell_coords(pos);
+            let cell = y * self.cols + x;
+            self.starts[cell + 1] += 1;
+            self.unsorted.push((cell, (i, pos, radius)));
+            self.max_radius = self.max_radius.max(radius);
+        }
+
+        for i in 1..self.starts.len() {
+            self.starts[i] += self.starts[i - 1];
+        }
+
+        // Counting sort, each cell's entries keep their insertion order
+        self.entries.clear();
+        self.entries
+            .resize(self.unsorted.len(), (0, Vec2::ZERO, 0.));
+        self.cursors.clone_from(&self.starts);
+        for &(cell, entry) in &self.unsorted {
+            self.entries[self.cursors[cell]] = entry;
+            self.cursors[cell] += 1;
+        }
+    }
+
+    /// Every entry in the cells that `center`±`reach` touches.
+    fn candidates(&self, center: Vec2, reach: f32) -> impl Iterator<Item = &(usize, Vec2, f32)> {
+        let (x0, y0) = self.cell_coords(center - Vec2::splat(reach));
+        let (x1, y1) = self.cell_coords(center + Vec2::splat(reach));
+
+        (y0..=y1).flat_map(move |y| {
+            let row = y * self.cols;
+            self.entries[self.starts[row + x0]..self.starts[row + x1 + 1]].iter()
+        })
+    }
+
+    /// Indices of the entities whose circle overlaps the given one.
+    pub fn overlapping(&self, center: Vec2, radius: f32) -> impl Iterator<Item = usize> + '_ {
+        self.candidates(center, radius + self.max_radius)
+            .filter(move |(_, pos, r)| (*pos - center).length() < radius + r)
+            .map(|(i, _, _)| *i)
+    }
+
+    /// Indices of the entities whose center is closer than `range`.
+    pub fn within(&self, center: Vec2, range: f32) -> impl Iterator<Item = usize> + '_ {
+        self.candidates(center, range)
+            .filter(move |(_, pos, _)| (*pos - center).length() < range)
+            .map(|(i, _, _)| *i)
+    }
+
+    /// Fills `found` with `(squared distance, index)` of the `n` entities
+    /// closest to `center` that are closer than `range` and pass `filter`, in
+    /// no particular order. Looks at the cells ring by ring around `center`
+    /// and stops as soon as no further cell can hold anything closer, so
+    /// crowds don't have to be scanned whole.
+    pub fn nearest(
+        &self,
+        center: Vec2,
+        range: f32,
+        n: usize,
+        mut filter: impl FnMut(usize) -> bool,
+        found: &mut Vec<(f32, usize)>,
+    ) {
+        found.clear();
+        if n == 0 {
+            return;
+        }
+        let (cx, cy) = self.cell_coords(center);
+
+        for r in 0.. {
+            let (x0, x1) = (cx.saturating_sub(r), (cx + r).min(self.cols - 1));
+            let (y0, y1) = (cy.saturating_sub(r), (cy + r).min(self.rows - 1));
+            for y in y0..=y1 {
+                for x in x0..=x1 {
+                    // Inner cells were done by the previous rings
+                    if x.abs_diff(cx) < r && y.abs_diff(cy) < r {
+                        continue;
+                    }
+                    let cell = y * self.cols + x;
+                    for &(i, pos, _) in &self.entries[self.starts[cell]..self.starts[cell + 1]] {
+                        let dist = pos.distance_squared(center);
+                        if dist < range * range && filter(i) {
+                            found.push((dist, i));
+                        }
+                    }
+                }
+            }
+
+            let farthest = if found.len() >= n {
+                found.select_nth_unstable_by(n - 1, |a, b| a.0.total_cmp(&b.0));
+                found.truncate(n);
+                found[n - 1].0
+            } else {
+                f32::INFINITY
+            };
+
+            // Closest that an entity in the cells left can be. The border
+            // cells also hold everything beyond them, so once a side
+            // reaches the border there is nothing left on that side.
+            let lo = self.min + Vec2::new(x0 as f32, y0 as f32) * self.cell_size;
+            let hi = self.min + Vec2::new((x1 + 1) as f32, (y1 + 1) as f32) * self.cell_size;
+            let mut left = f32::INFINITY;
+            if x0 > 0 {
+                left = left.min(center.x - lo.x);
+            }
+            if y0 > 0 {
+                left = left.min(center.y - lo.y);
+            }
+            if x1 < self.cols - 1 {
+                left = left.min(hi.x - center.x);
+            }
+            if y1 < self.rows - 1 {
+                left = left.min(hi.y - center.y);
+            }
+            if left >= range || farthest <= left * left {
+                break;
+            }
+        }
+    }
+
+    /// Every pair of overlapping entities, once, lowest index first.
+    pub fn pairs(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.entries.iter().flat_map(move |&(i, pos, radius)| {
+            self.overlapping(pos, radius)
+                .filter(move |&j| j > i)
+                .map(move |j| (i, j))
+        })
+    }
+
+    pub const fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(entities: &[(Vec2, f32)]) -> SpatialHash {
+        let mut grid = SpatialHash::new(Vec2::ZERO, Vec2::new(10., 10.), 2.);
+        grid.rebuild(entities.iter().copied());
+        grid
+    }
+
+    fn sorted(indices: impl Iterator<Item = usize>) -> Vec<usize> {
+        let mut indices: Vec<usize> = indices.collect();
+        indices.sort_unstable();
+        indices
+    }
+
+    #[test]
+    fn overlapping_counts_the_radius_of_both() {
+        let grid = grid(&[
+            (Vec2::new(1., 1.), 0.5),
+            (Vec2::new(3., 1.), 1.5),
+            (Vec2::new(9., 9.), 0.5),
+        ]);
+
+        assert_eq!(grid.len(), 3);
+        // The second one is only reached through its big radius
+        assert_eq!(sorted(grid.overlapping(Vec2::new(1., 1.), 0.6)), [0, 1]);
+        assert_eq!(sorted(grid.overlapping(Vec2::new(9., 8.), 0.6)), [2]);
+        assert!(grid.overlapping(Vec2::new(6., 6.), 0.5).next().is_none());
+    }
+
+    #[test]
+    fn within_only_counts_centers() {
+        let grid = grid(&[(Vec2::new(1., 1.), 3.), (Vec2::new(4., 1.), 0.1)]);
+
+        assert_eq!(sorted(grid.within(Vec2::new(1., 1.), 3.5)), [0, 1]);
+        assert_eq!(sorted(grid.within(Vec2::new(5., 1.), 2.)), [1]);
+    }
+
+    #[test]
+    fn pairs_are_found_once() {
+        let grid = grid(&[
+            (Vec2::new(1., 1.), 0.5),
+            (Vec2::new(1.8, 1.), 0.5),
+            (Vec2::new(2.6, 1.), 0.5),
+            (Vec2::new(8., 8.), 0.5),
+        ]);
+
+        let mut pairs: Vec<(usize, usize)> = grid.pairs().collect();
+        pairs.sort_unstable();
+        assert_eq!(pairs, [(0, 1), (1, 2)]);
+    }
+
+    #[test]
+    fn nearest_keeps_the_closest_in_range() {
+        let entities: Vec<(Vec2, f32)> = (0..10)
+            .map(|i| (Vec2::new(1. + i as f32 * 0.9, 5.), 0.1))
+            .collect();
+        let grid = grid(&entities);
+        let mut found = Vec::new();
+
+        grid.nearest(Vec2::new(1., 5.), 20., 3, |i| i != 0, &mut found);
+        assert_eq!(sorted(found.iter().map(|&(_, i)| i)), [1, 2, 3]);
+
+        // Only the first two are closer than the range
+        grid.nearest(Vec2::new(1., 5.), 2., 5, |i| i != 0, &mut found);
+        assert_eq!(sorted(found.iter().map(|&(_, i)| i)), [1, 2]);
+
+        // From outside the grid, where the border cells hold everything
+        grid.nearest(Vec2::new(30., 5.), 40., 1, |_| true, &mut found);
+        assert_eq!(sorted(found.iter().map(|&(_, i)| i)), [9]);
+    }
+
+    #[test]
+    fn outside_entities_are_kept_in_the_border_cells() {
+        let grid = grid(&[(Vec2::new(-5., 5.), 0.5), (Vec2::new(15., 15.), 0.5)]);
+
+        assert_eq!(sorted(grid.overlapping(Vec2::new(-5., 5.), 0.1)), [0]);
+        assert_eq!(sorted(grid.within(Vec2::new(14., 14.), 2.)), [1]);
+    }
+}
@@ -0,0 +1,121 @@
+// Steering behaviours (seek, arrive, pursuit, separation, alignment and
+// cohesion) combined into one acceleration per tick. `World` gathers the
+// target and the neighbors, the entity only integrates the result.
+
+use macroquad::math::Vec2;
+use nanoserde::DeRon;
+
+/// Tuning of a steering agent, a weight of 0 turns a behaviour off. All
+/// speeds are in world units per tick. Each follower archetype sets its own
+/// in `assets/enemies.ron`.
+#[derive(Clone, Copy, Debug, PartialEq, DeRon)]
+pub struct SteeringParams {
+    /// The archetype's speed
+    #[nserde(skip)]
+    pub max_speed: f32,
+    /// Longest acceleration applied in a tick
+    pub max_force: f32,
+
+    /// Weight of seeking (or pursuing) the target
+    pub seek: f32,
+    /// Slows down closer than this to the target, 0 to never slow down
+    #[nserde(default)]
+    pub arrive_radius: f32,
+    /// Aims where the target will be in up to that many ticks
+    #[nserde(default)]
+    pub lead: f32,
+    /// Aims this far to the side of the target's heading, the sign picks
+    /// the side
+    #[nserde(default)]
+    pub flank: f32,
+
+    /// Neighbors further than this are ignored by the flocking behaviours
+    pub neighbor_radius: f32,
+    pub separation: f32,
+    /// Only neighbors closer than this push away
+    pub separation_radius: f32,
+    pub alignment: f32,
+    pub cohesion: f32,
+}
+
+/// Something moving, seen by a steering agent.
+#[derive(Clone, Copy, Debug)]
+pub struct Body {
+    pub pos: Vec2,
+    pub speed: Vec2,
+}
+
+/// Change of velocity that heads straight for `target` at full speed.
+pub fn seek(agent: Body, target: Vec2, max_speed: f32) -> Vec2 {
+    (target - agent.pos).normalize_or_zero() * max_speed - agent.speed
+}
+
+/// Like `seek`, but slows down inside `radius` to stop on `target`.
+pub fn arrive(agent: Body, target: Vec2, max_speed: f32, radius: f32) -> Vec2 {
+    let offset = target - agent.pos;
+    let distance = offset.length();
+    if radius <= 0. || distance >= radius {
+        return seek(agent, target, max_speed);
+    }
+    offset.normalize_or_zero() * max_speed * distance / radius - agent.speed
+}
+
+/// Where to aim to catch `target`, `lead` ticks ahead at most and `flank`
+/// units to its side.
+pub fn pursuit_point(agent: Body, target: Body, max_speed: f32, lead: f32, flank: f32) -> Vec2 {
+    let distance = (target.pos - agent.pos).length();
+    let ticks = if max_speed > 0. {
+        (distance / max_speed).min(lead)
+    } else {
+        0.
+    };
+    let predicted = target.pos + target.speed * ticks;
+
+    let heading = target
+        .speed
+        .try_normalize()
+        .unwrap_or_else(|| (target.pos - agent.pos).normalize_or_zero());
+    predicted + heading.perp() * flank
+}
+
+/// Pushes away from neighbors closer than `radius`, harder the closer.
+pub fn separation(agent: Body, neighbors: &[Body], radius: f32) -> Vec2 {
+    neighbors
+        .iter()
+        .filter_map(|n| {
+            let away = agent.pos - n.pos;
+            let distance = away.length();
+            (distance > 0. && distance < radius).then(|| away / (distance * distance))
+        })
+        .fold(Vec2::ZERO, |sum, push| sum + push)
+}
+
+/// Matches the average velocity of the neighbors.
+pub fn alignment(agent: Body, neighbors: &[Body]) -> Vec2 {
+    if neighbors.is_empty() {
+        return Vec2::ZERO;
+    }
+    neighbors.iter().fold(Vec2::ZERO, |sum, n| sum + n.speed) / neighbors.len() as f32 - agent.speed
+}
+
+/// Heads for the center of the neighbors.
+pub fn cohesion(agent: Body, neighbors: &[Body], max_speed: f32) -> Vec2 {
+    if neighbors.is_empty() {
+        return Vec2::ZERO;
+    }
+    let center = neighbors.iter().fold(Vec2::ZERO, |sum, n| sum + n.pos) / neighbors.len() as f32;
+    seek(agent, center, max_speed)
+}
+
+/// Weighted sum of every behaviour, capped to `max_force`. `neighbors`
+/// should already be limited to `neighbor_radius`.
+pub fn steer(params: &SteeringParams, agent: Body, target: Body, neighbors: &[Body]) -> Vec2 {
+    let aim = pursuit_point(agent, target, params.max_speed, params.lead, params.flank);
+
+    let force = arrive(agent, aim, params.max_speed, params.arrive_radius) * params.seek
+        + separation(agent, neighbors, params.separation_radius) * params.separation
+        + alignment(agent, neighbors) * params.alignment
+        + cohesion(agent, neighbors, params.max_speed) * params.cohesion;
+
+    force.clamp_length_max(params.max_force)
+}
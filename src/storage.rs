@@ -0,0 +1,50 @@
+// Small key/value store: one file per key in the user's config directory on
+// desktop, browser `localStorage` on the web build (see `web/storage.js`).
+
+#[cfg(not(target_arch = "wasm32"))]
+fn path(key: &str) -> Option<std::path::PathBuf> {
+    Some(
+        dirs::config_dir()?
+            .join("unglitched")
+            .join(format!("{key}.json")),
+    )
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load(key: &str) -> Option<String> {
+    std::fs::read_to_string(path(key)?).ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save(key: &str, data: &str) {
+    let Some(path) = path(key) else {
+        return;
+    };
+    let result = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|()| std::fs::write(&path, data));
+    if let Err(err) = result {
+        macroquad::logging::warn!("Couldn't save {}: {}", path.display(), err);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+extern "C" {
+    fn unglitched_storage_len(key: *const u8, key_len: usize) -> i32;
+    fn unglitched_storage_read(key: *const u8, key_len: usize, dst: *mut u8);
+    fn unglitched_storage_write(key: *const u8, key_len: usize, data: *const u8, len: usize);
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn load(key: &str) -> Option<String> {
+    let len = unsafe { unglitched_storage_len(key.as_ptr(), key.len()) };
+    let mut buffer = vec![0; usize::try_from(len).ok()?];
+    unsafe { unglitched_storage_read(key.as_ptr(), key.len(), buffer.as_mut_ptr()) };
+    String::from_utf8(buffer).ok()
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn save(key: &str, data: &str) {
+    unsafe { unglitched_storage_write(key.as_ptr(), key.len(), data.as_ptr(), data.len()) };
+}
@@ -0,0 +1,21 @@
+// Fixtures shared by the unit tests. They use the data files built into the
+// game, never the tuned copies `load` would pick up from the working directory.
+
+use crate::{archetypes::Archetypes, director::WaveScript, world::World};
+
+pub fn archetypes() -> Archetypes {
+    Archetypes::from_ron(include_str!("../assets/enemies.ron")).unwrap()
+}
+
+pub fn script(archetypes: &Archetypes) -> WaveScript {
+    WaveScript::from_ron(include_str!("../assets/waves.ron"), archetypes).unwrap()
+}
+
+/// A world with its run started, as if the player just entered their name.
+pub fn world(seed: u64) -> World {
+    let archetypes = archetypes();
+    let script = script(&archetypes);
+    let mut world = World::with_seed(seed, archetypes, script);
+    world.has_game_started = true;
+    world
+}
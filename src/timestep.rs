@@ -0,0 +1,32 @@
+// Fixed-rate simulation clock: the frontend accumulates real frame time and
+// runs as many `World::tick`s as fit, whatever the monitor refresh rate.
+
+pub const TICK_RATE: f32 = 60.;
+pub const TICK_DT: f32 = 1. / TICK_RATE;
+/// Longest frame taken into account, so a stall (window dragged, debugger)
+/// doesn't make the simulation sprint to catch up.
+const MAX_FRAME_TIME: f32 = 0.25;
+
+#[derive(Default)]
+pub struct FixedTimestep {
+    accumulator: f32,
+}
+
+impl FixedTimestep {
+    /// Adds `frame_time` seconds and returns how many ticks are now due.
+    pub fn advance(&mut self, frame_time: f32) -> u32 {
+        self.accumulator += frame_time.min(MAX_FRAME_TIME);
+        let ticks = (self.accumulator / TICK_DT) as u32;
+        self.accumulator -= ticks as f32 * TICK_DT;
+        ticks
+    }
+
+    /// How far we are between the last tick and the next one, from 0 to 1.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / TICK_DT).clamp(0., 1.)
+    }
+
+    pub const fn reset(&mut self) {
+        self.accumulator = 0.;
+    }
+}
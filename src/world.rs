@@ -1,22 +1,39 @@
 use crate::{
+    abilities::{self, Effects, Loadout, SHIELD_TICKS},
     achievements,
-    entities::{self, Entity, EntityType, WORLD_HEIGHT, WORLD_WIDTH},
-    resources::{self, Resources},
-    GameState, GlitchEffect,
+    archetypes::{Archetypes, Behavior},
+    director::{Director, WaveScript},
+    entities::{self, Entity, EntityType},
+    events::{Event, ItemKind},
+    glitches::{GlitchFlags, Glitches},
+    input::Input,
+    kernel::{Kernel, KERNEL_TIME},
+    rng::{fnv1a, Rng},
+    spatial::SpatialHash,
+    steering::{self, Body},
+    timestep::TICK_DT,
 };
 
-use macroquad::{
-    audio::{play_sound, stop_sound, PlaySoundParams},
-    prelude::*,
-};
+use macroquad::prelude::*;
 
 pub const DESTROY_RANGE: f32 = 5.;
-const BULLET_SPAWN_TIME: u32 = 60;
-const FOLLOWER_SPAWN_TIME: u32 = 200;
-const PATH_SPAWN_TIME: u32 = 150;
 const INSTABILITY_UP: u32 = 1;
 const MAX_UNSTABILITY: u32 = 5;
-const GLITCH_SPEED: u32 = 10;
+const GRID_CELL_SIZE: f32 = 2.;
+/// Followers only flock with their nearest neighbors, so big crowds stay cheap
+const MAX_NEIGHBORS: usize = 12;
+const SCORE_PER_ENEMY: u32 = 10;
+/// While the score pickup is in effect
+const SCORE_MULTIPLIER: u32 = 2;
+const SCORE_BOOST_TICKS: u32 = 600;
+/// Player acceleration multiplier while the speed pickup is in effect
+const SPEED_BOOST: f32 = 1.6;
+const SPEED_BOOST_TICKS: u32 = 300;
+const MAGNET_TICKS: u32 = 600;
+/// Items closer than this to the player fly to it while the magnet is on
+const MAGNET_RANGE: f32 = 12.;
+/// Item speed toward the player, in world units per tick
+const MAGNET_PULL: f32 = 0.3;
 
 pub struct World {
     pub player: Entity,
@@ -24,445 +41,721 @@ pub struct World {
     pub items: Vec<Entity>,
     pub hp: u8,
     pub mana: u8,
-    pub bullet_spawn_timer: u32,
-    pub follower_spawn_timer: u32,
-    pub pather_spawn_timer: u32,
+    /// Points from destroyed enemies
+    pub score: u32,
+    pub archetypes: Archetypes,
+    pub director: Director,
     pub achievements: achievements::Achievements,
     pub instability: u32,
-    pub glitch_frequency_counter: u32,
-    pub x_direction: i32,
-    pub y_direction: i32,
-    pub duplicate: Option<Entity>,
+    pub glitches: Glitches,
+    /// The boss, while it's alive
+    pub kernel: Option<Kernel>,
+    /// Only one Kernel per run
+    pub kernel_spawned: bool,
+    pub glitch_flags: GlitchFlags,
+    pub loadout: Loadout,
+    pub effects: Effects,
     pub has_game_started: bool,
-    pub glitch_effect: GlitchEffect,
     pub power_up_timer: u32,
-    pub show_tutorial_1: bool,
-    pub show_tutorial_2: bool,
-    pub show_tutorial_3: bool,
-    pub show_tutorial_4: bool,
-    pub show_tutorial_5: bool,
-    pub show_tutorial_2_1: bool,
-    pub show_tutorial_2_2: bool,
-    pub show_tutorial_2_3: bool,
-    pub show_tutorial_2_4: bool,
-    pub show_tutorial_2_5: bool,
-    pub show_tutorial_2_6: bool,
-    pub show_credits_1: bool,
-    pub show_credits_2: bool,
-    pub show_input_popup: bool,
-    pub disable_tutorial_2_x: bool,
     pub timer: f32,
-    pub show_credits: bool,
-    pub show_final_bsod: bool,
+    pub seed: u64,
+    pub rng: Rng,
+    /// Set from the console, the player can't be hurt. Survives `reset`
+    pub godmode: bool,
+    /// Indexed by position in `enemies`, only up to date right after
+    /// `index_enemies`
+    pub enemy_grid: SpatialHash,
+    /// Indexed by position in `items`, rebuilt before the pickups
+    pub item_grid: SpatialHash,
+}
+
+/// Covers the arena and the ring enemies spawn on.
+fn new_grid() -> SpatialHash {
+    let margin = Vec2::splat(entities::SPAWN_DIST + 1.);
+    SpatialHash::new(
+        entities::CENTER - margin,
+        entities::CENTER + margin,
+        GRID_CELL_SIZE,
+    )
 }
 
 const PLAYER_SPEED: f32 = 0.05;
 
 impl World {
-    pub fn new() -> Self {
+    /// Same seed, data files and inputs always play out the same run.
+    pub fn with_seed(seed: u64, archetypes: Archetypes, script: WaveScript) -> Self {
         Self {
             player: Entity::new_player(),
             enemies: Vec::new(),
             items: Vec::new(),
             hp: 3,
             mana: 3,
-            bullet_spawn_timer: 0,
-            follower_spawn_timer: 0,
-            pather_spawn_timer: 0,
+            score: 0,
+            director: Director::new(&archetypes, script),
+            archetypes,
             achievements: achievements::Achievements::new(),
             instability: 0,
-            glitch_frequency_counter: 0,
-            x_direction: 0,
-            y_direction: 0,
-            duplicate: None,
+            glitches: Glitches::new(),
+            kernel: None,
+            kernel_spawned: false,
+            glitch_flags: GlitchFlags::default(),
+            loadout: Loadout::new(),
+            effects: Effects::default(),
             has_game_started: false,
-            glitch_effect: GlitchEffect::new(),
             power_up_timer: 0,
-            show_tutorial_1: true,
-            show_tutorial_2: false,
-            show_tutorial_3: false,
-            show_tutorial_4: false,
-            show_tutorial_5: false,
-            show_tutorial_2_1: false,
-            show_tutorial_2_2: false,
-            show_tutorial_2_3: false,
-            show_tutorial_2_4: false,
-            show_tutorial_2_5: false,
-            show_tutorial_2_6: false,
-            show_credits_1: false,
-            show_credits_2: false,
-            show_input_popup: false,
-            disable_tutorial_2_x: false,
             timer: 0.,
-            show_credits: false,
-            show_final_bsod: false,
+            seed,
+            rng: Rng::new(seed),
+            godmode: false,
+            enemy_grid: new_grid(),
+            item_grid: new_grid(),
         }
     }
 
-    pub const fn popup_shown(&self) -> bool {
-        self.show_input_popup
-            || self.show_tutorial_2_2
-            || self.show_tutorial_2_1
-            || self.show_tutorial_1
-            || self.show_tutorial_2
-            || self.show_tutorial_3
-            || self.show_tutorial_4
-            || self.show_tutorial_5
-            || self.show_tutorial_2_3
-            || self.show_tutorial_2_4
-            || self.show_tutorial_2_5
-            || self.show_tutorial_2_6
-            || self.show_credits_1
-            || self.show_credits_2
+    /// Identifies the data files the run depends on, a replay only plays
+    /// out the same with the same.
+    pub fn data_hash(&self) -> u64 {
+        let hashes = [self.archetypes.hash, self.director.script.hash];
+        fnv1a(&hashes.map(u64::to_le_bytes).concat())
     }
 
-    pub fn raise_unstability(&mut self, resources: &Resources) {
+    pub fn raise_unstability(&mut self, events: &mut Vec<Event>) {
         self.instability += INSTABILITY_UP;
-        play_sound(
-            resources.small_bug_sound,
-            PlaySoundParams {
-                looped: false,
-                volume: 1.,
-            },
-        );
+        events.push(Event::InstabilityRaised);
     }
 
-    pub fn tick(
-        &mut self,
-        resources: &Resources,
-        game_state: &mut GameState,
-        bsod_message: &mut String,
-    ) {
-        self.timer += 1. / 60.;
+    /// Returns `true` if the bug wasn't known yet, in which case its
+    /// achievement is now unlocked.
+    pub fn bug_found(&mut self, id: &str, events: &mut Vec<Event>) -> bool {
+        if !self.achievements.unlock(id) {
+            return false;
+        }
+        events.push(Event::AchievementUnlocked(id.to_owned()));
+        true
+    }
 
-        if self.power_up_timer > 0 {
-            self.power_up_timer -= 1;
+    /// The first time a bug is found it crashes the computer, afterwards it
+    /// only makes things less stable.
+    pub fn trigger_bug(&mut self, id: &str, events: &mut Vec<Event>) {
+        if self.bug_found(id, events) {
+            let message = self.achievements.bsod_message(id);
+            self.bsod(&message, events);
+        } else {
+            self.raise_unstability(events);
         }
+    }
 
-        let mut to_raise_unstability = false;
+    /// Advances the simulation by `TICK_DT`. Doesn't touch any macroquad
+    /// global (input, audio, rand), everything goes through `input`,
+    /// `self.rng` and `events`.
+    pub fn tick(&mut self, input: &Input, events: &mut Vec<Event>) {
+        let first_event = events.len();
+        self.timer += TICK_DT;
 
-        if self.bullet_spawn_timer > BULLET_SPAWN_TIME {
-            self.bullet_spawn_timer = 0;
-            self.enemies
-                .push(Entity::new_random_bullet(self.player.pos));
-        } else {
-            self.bullet_spawn_timer += 1;
+        self.player.prev_pos = self.player.pos;
+        for e in self.enemies.iter_mut().chain(&mut self.items) {
+            e.prev_pos = e.pos;
         }
 
-        if self.follower_spawn_timer > FOLLOWER_SPAWN_TIME {
-            self.follower_spawn_timer = 0;
-            self.enemies
-                .push(Entity::new_random_follower(self.player.pos));
+        if self.power_up_timer > 0 {
+            self.power_up_timer -= 1;
+        }
+        self.effects.tick();
+
+        if let Some(kernel) = &mut self.kernel {
+            kernel.tick(
+                self.player.pos,
+                &self.archetypes,
+                &mut self.rng,
+                &mut self.enemies,
+            );
+        } else if !self.kernel_spawned && self.timer >= KERNEL_TIME {
+            self.kernel = Some(Kernel::new());
+            self.kernel_spawned = true;
+            events.push(Event::KernelSpawned);
         } else {
-            self.follower_spawn_timer += 1;
+            self.spawn_enemies();
         }
 
-        if self.pather_spawn_timer == PATH_SPAWN_TIME {
-            self.pather_spawn_timer = 0;
-            self.enemies.push(Entity::new_random_pather());
+        let (right, left, down, up) = if self.glitch_flags.inverted_controls {
+            (input.left, input.right, input.up, input.down)
         } else {
-            self.pather_spawn_timer += 1;
+            (input.right, input.left, input.down, input.up)
+        };
+        let acceleration = if self.effects.speed > 0 {
+            PLAYER_SPEED * SPEED_BOOST
+        } else {
+            PLAYER_SPEED
+        };
+        if right {
+            self.player.speed.x += acceleration;
         }
-
-        if is_key_down(KeyCode::D) {
-            self.player.speed.x += PLAYER_SPEED;
+        if left {
+            self.player.speed.x -= acceleration;
         }
-        if is_key_down(KeyCode::A) {
-            self.player.speed.x -= PLAYER_SPEED;
+        if down {
+            self.player.speed.y += acceleration;
         }
-        if is_key_down(KeyCode::S) {
-            self.player.speed.y += PLAYER_SPEED;
+        if up {
+            self.player.speed.y -= acceleration;
         }
-        if is_key_down(KeyCode::W) {
-            self.player.speed.y -= PLAYER_SPEED;
+
+        // Moved out like the glitches, abilities borrow the whole world
+        let mut loadout = std::mem::take(&mut self.loadout);
+        loadout.tick();
+        if let Some(slot) = input.select.checked_sub(1) {
+            loadout.select(usize::from(slot));
         }
-        if is_key_pressed(KeyCode::Space) {
-            self.power_destroy(resources, game_state, bsod_message);
+        if input.power {
+            loadout.cast(self, events);
         }
+        self.loadout = loadout;
 
         self.player.speed *= 0.9;
 
-        self.player.tick(Vec2::ZERO);
+        self.player.tick(Vec2::ZERO, None);
 
-        let mut display_bsod = false;
+        self.steer_followers();
+        let bullet = self.archetypes.base(Behavior::Bullet);
+        // Enemies in the field skip every other tick
+        let slow_field = (self.effects.slow % 2 == 1).then_some(self.player.pos);
+        let mut shots = Vec::new();
         for b in &mut self.enemies {
-            b.tick(self.player.pos);
-            if (b.pos - self.player.pos).length() < (self.player.radius + b.radius) {
-                play_sound(
-                    resources.hit_sound,
-                    PlaySoundParams {
-                        looped: false,
-                        volume: 0.5,
-                    },
-                );
-                if self.player.hit_anim == 0 {
-                    if let Some(new_hp) = self.hp.checked_sub(1) {
-                        self.hp = new_hp;
-                    } else {
-                        self.hp = 3;
-                        if self.achievements.achievements[2].unlocked {
-                            to_raise_unstability = true;
-                        } else {
-                            self.achievements.achievements[2].unlock();
-                            *bsod_message = self.achievements.achievements[2].name.to_owned();
-                            display_bsod = true;
-                        }
-                    }
-                    self.player.hit_anim = 10;
-                }
-                b.alive = false;
+            b.tick(self.player.pos, slow_field);
+            shots.extend(b.fire(self.player.pos, bullet));
+        }
+        self.enemies.extend(shots);
+
+        if self.effects.reflector > 0 {
+            abilities::reflect_bullets(self);
+        }
+        self.index_enemies();
+        self.friendly_fire(events);
+
+        let mut hits: Vec<usize> = self
+            .enemy_grid
+            .overlapping(self.player.pos, self.player.radius)
+            .filter(|&i| self.enemies[i].alive && !self.enemies[i].friendly)
+            .collect();
+        hits.sort_unstable();
+        for i in hits {
+            // Dashing goes right through enemies
+            if self.effects.dash > 0 {
+                continue;
             }
+            if self.effects.shield == 0 {
+                self.hurt_player(events);
+            }
+            let b = &mut self.enemies[i];
+            b.alive = false;
+            events.push(Event::EnemyCollided { pos: b.pos });
         }
 
-        for i in &mut self.items {
-            if (i.pos - self.player.pos).length() < (self.player.radius + i.radius) {
-                play_sound(
-                    resources.picking_item_sound,
-                    PlaySoundParams {
-                        looped: false,
-                        volume: 0.5,
-                    },
-                );
-                match &i.e_type {
-                    EntityType::HealItem => {
-                        if self.hp + 1 > 3 {
-                            self.hp = 0;
-                            if self.achievements.achievements[3].unlocked {
-                                to_raise_unstability = true;
-                            } else {
-                                self.achievements.achievements[3].unlock();
-                                *bsod_message = self.achievements.achievements[3].name.to_owned();
-                                display_bsod = true;
-                            }
-                        } else {
-                            self.hp += 1;
-                        }
-                    }
-
-                    &EntityType::ManaItem => {
-                        if self.mana + 1 > 3 {
-                            self.mana = 0;
-                            if self.achievements.achievements[5].unlocked {
-                                to_raise_unstability = true;
-                            } else {
-                                self.achievements.achievements[5].unlock();
-                                *bsod_message = self.achievements.achievements[5].name.to_owned();
-                                display_bsod = true;
-                            }
-                        } else {
-                            self.mana += 1;
-                        }
-                    }
-                    _ => unreachable!(),
+        if let Some(kernel) = &self.kernel {
+            let offset = self.player.pos - kernel.pos;
+            let min_dist = kernel.radius + self.player.radius;
+            if offset.length() < min_dist {
+                // Pushed out of the body so a single touch only hurts once
+                self.player.pos = kernel.pos + offset.try_normalize().unwrap_or(Vec2::Y) * min_dist;
+                if !self.effects.protected() {
+                    self.hurt_player(events);
                 }
-                i.alive = false;
             }
         }
 
-        if self.player.pos.y < -2. {
-            if self.achievements.achievements[6].unlocked {
-                to_raise_unstability = true;
-                self.player.pos = entities::CENTER;
+        for item in &mut self.items {
+            let pull = self.player.pos - item.pos;
+            item.speed = if self.effects.magnet > 0 && pull.length() < MAGNET_RANGE {
+                pull.clamp_length_max(MAGNET_PULL)
             } else {
-                self.achievements.achievements[6].unlock();
-                *bsod_message = self.achievements.achievements[6].name.to_owned();
-                display_bsod = true;
-            }
+                Vec2::ZERO
+            };
+            item.tick(self.player.pos, None);
+        }
+
+        self.item_grid
+            .rebuild(self.items.iter().map(|i| (i.pos, i.radius)));
+        let mut picked: Vec<usize> = self
+            .item_grid
+            .overlapping(self.player.pos, self.player.radius)
+            .filter(|&i| self.items[i].alive)
+            .collect();
+        picked.sort_unstable();
+        for i in picked {
+            let item = &mut self.items[i];
+            item.alive = false;
+            let EntityType::Item { kind, .. } = item.e_type else {
+                unreachable!()
+            };
+            self.pick_item(kind, events);
+        }
+
+        if self.player.pos.y < -2. {
+            self.player.pos = entities::CENTER;
+            self.player.prev_pos = self.player.pos;
+            events.push(Event::LeftArena);
         }
 
+        let mut shards = Vec::new();
         self.enemies.retain(|e| {
+            if e.bursts() {
+                shards.extend(e.splitter_shards(bullet));
+            }
             if !e.alive {
-                if let EntityType::Follower = e.e_type {
-                    let rand_num = rand::gen_range(0., 100.);
-
-                    if rand_num < 12.5 {
-                        self.items.push(Entity::new_heal_item());
-                    } else if rand_num < 25. {
-                        self.items.push(Entity::new_mana_item());
-                    }
+                if let Some(kind) = self.archetypes.roll_drop(e.archetype, &mut self.rng) {
+                    self.items.push(Entity::new_item(kind, &mut self.rng));
                 }
             }
             e.alive
         });
+        self.enemies.extend(shards);
         self.items.retain(|e| e.alive);
 
-        if self.instability > MAX_UNSTABILITY {
-            self.achievements.achievements[1].unlock();
-            *bsod_message = self.achievements.achievements[1].name.to_owned();
-            self.bsod(game_state, resources);
-        }
+        let destroyed = events[first_event..]
+            .iter()
+            .filter(|e| matches!(e, Event::EnemyDestroyed { .. }))
+            .count() as u32;
+        let multiplier = if self.effects.score > 0 {
+            SCORE_MULTIPLIER
+        } else {
+            1
+        };
+        self.score += destroyed * SCORE_PER_ENEMY * multiplier;
 
-        if to_raise_unstability {
-            self.raise_unstability(resources);
-        }
+        self.apply_bug_rules(first_event, events);
 
-        if display_bsod {
-            self.bsod(game_state, resources);
-        }
+        // The registry is moved out so glitches can borrow the whole world
+        let mut glitches = std::mem::take(&mut self.glitches);
+        glitches.tick(self, events);
+        self.glitches = glitches;
+    }
 
-        if self.glitch_frequency_counter == 0 {
-            match self.instability {
-                1 => {
-                    self.initialize_glitch(0.01);
-                    self.glitch_effect.set(20, 0.5);
+    fn pick_item(&mut self, kind: ItemKind, events: &mut Vec<Event>) {
+        events.push(Event::ItemPicked(kind));
+        match kind {
+            ItemKind::Heal => {
+                if self.hp + 1 > 3 {
+                    self.hp = 0;
+                    events.push(Event::HpOverflow);
+                } else {
+                    self.hp += 1;
                 }
-                2 => {
-                    self.initialize_glitch(0.05);
-                    self.glitch_effect.set(20, 1.);
-                }
-                3 => {
-                    self.initialize_glitch(0.07);
-                    self.glitch_effect.set(20, 2.);
-                }
-                4 => {
-                    self.initialize_glitch(0.1);
-                    self.glitch_effect.set(20, 4.);
-                }
-                5 => {
-                    self.initialize_glitch(1.);
-                    self.glitch_effect.set(20, 8.);
-                }
-                _ => (),
             }
-        } else {
-            if let Some(to_duplicate) = &mut self.duplicate {
-                to_duplicate.tick(self.player.pos);
-            }
-            if let Some(to_duplicate) = &self.duplicate {
-                if self.glitch_frequency_counter % GLITCH_SPEED == 0 {
-                    self.glitch(
-                        (*to_duplicate).clone(),
-                        self.x_direction,
-                        self.y_direction,
-                        resources,
-                    );
+            ItemKind::Mana => {
+                if self.mana + 1 > 3 {
+                    self.mana = 0;
+                    events.push(Event::ManaOverflow);
+                } else {
+                    self.mana += 1;
                 }
             }
-            self.glitch_frequency_counter -= 1;
+            ItemKind::Score => self.effects.score = SCORE_BOOST_TICKS,
+            ItemKind::Speed => self.effects.speed = SPEED_BOOST_TICKS,
+            ItemKind::Patch => self.instability = self.instability.saturating_sub(1),
+            ItemKind::Magnet => self.effects.magnet = MAGNET_TICKS,
+            ItemKind::Shield => self.effects.shield = SHIELD_TICKS,
         }
     }
 
-    pub fn glitch(
-        &mut self,
-        mut clone: Entity,
-        x_direction: i32,
-        y_direction: i32,
-        resources: &Resources,
-    ) {
-        if y_direction < 0 {
-            clone.pos.y -= 0.3;
-        } else {
-            clone.pos.y += 0.3;
+    fn hurt_player(&mut self, events: &mut Vec<Event>) {
+        if self.godmode {
+            return;
         }
-        if x_direction < 0 {
-            clone.pos.x -= 0.3;
-        } else {
-            clone.pos.x += 0.3;
-        }
-        clone.is_clone = true;
-        play_sound(
-            resources.glitch_sound,
-            PlaySoundParams {
-                looped: false,
-                volume: 0.2,
-            },
+        events.push(Event::PlayerHit);
+        if self.player.hit_anim == 0 {
+            self.director.player_hit();
+            if let Some(new_hp) = self.hp.checked_sub(1) {
+                self.hp = new_hp;
+            } else {
+                self.hp = 3;
+                events.push(Event::HpUnderflow);
+            }
+            self.player.hit_anim = 10;
+        }
+    }
+
+    /// Regular spawns, paused while the Kernel is around.
+    fn spawn_enemies(&mut self) {
+        self.director.tick(
+            &self.archetypes,
+            self.timer,
+            self.hp,
+            self.player.pos,
+            &mut self.rng,
+            &mut self.enemies,
         );
-        self.enemies.push(clone.clone());
-        self.duplicate = Some(clone);
     }
 
-    pub fn initialize_glitch(&mut self, percentage: f32) {
-        for b in &self.enemies {
-            if b.pos.x > 0. && b.pos.x < WORLD_WIDTH && b.pos.y > 0. && b.pos.y < WORLD_HEIGHT {
-                if rand::gen_range(0., 100.) < percentage {
-                    if !b.is_clone {
-                        self.duplicate = Some(b.clone());
-                        self.x_direction = rand::gen_range(-1, 1);
-                        self.y_direction = rand::gen_range(-1, 1);
-                        self.glitch_frequency_counter = GLITCH_SPEED * 6;
-                        break;
-                    }
-                }
+    /// Sets the steering force of every follower for this tick. Followers
+    /// chase the player and flock with the other followers around them.
+    fn steer_followers(&mut self) {
+        self.index_enemies();
+        let target = self.player.body();
+
+        let mut nearest = Vec::new();
+        let mut neighbors: Vec<Body> = Vec::with_capacity(MAX_NEIGHBORS);
+        let forces: Vec<Option<Vec2>> = self
+            .enemies
+            .iter()
+            .enumerate()
+            .map(|(i, e)| {
+                let EntityType::Follower { steering, .. } = &e.e_type else {
+                    return None;
+                };
+                self.enemy_grid.nearest(
+                    e.pos,
+                    steering.neighbor_radius,
+                    MAX_NEIGHBORS,
+                    |j| j != i && matches!(self.enemies[j].e_type, EntityType::Follower { .. }),
+                    &mut nearest,
+                );
+                neighbors.clear();
+                neighbors.extend(nearest.iter().map(|&(_, j)| self.enemies[j].body()));
+                Some(steering::steer(steering, e.body(), target, &neighbors))
+            })
+            .collect();
+
+        for (e, new_force) in self.enemies.iter_mut().zip(forces) {
+            if let (EntityType::Follower { force, .. }, Some(new_force)) =
+                (&mut e.e_type, new_force)
+            {
+                *force = new_force;
+            }
+        }
+    }
+
+    /// Reflected bullets destroy the enemies they touch, and themselves.
+    /// Needs an up to date `enemy_grid`.
+    fn friendly_fire(&mut self, events: &mut Vec<Event>) {
+        for i in 0..self.enemies.len() {
+            let bullet = &self.enemies[i];
+            if !bullet.friendly || !bullet.alive {
+                continue;
+            }
+            let mut targets: Vec<usize> = self
+                .enemy_grid
+                .overlapping(bullet.pos, bullet.radius)
+                .filter(|&j| self.enemies[j].alive && !self.enemies[j].friendly)
+                .collect();
+            targets.sort_unstable();
+            let Some(&j) = targets.first() else {
+                continue;
+            };
+
+            self.enemies[i].alive = false;
+            let b = &mut self.enemies[j];
+            if b.hp > 1 {
+                b.hp -= 1;
+                b.hit_anim = 10;
+            } else {
+                b.destroy();
+                events.push(Event::EnemyDestroyed { pos: b.pos });
             }
         }
     }
 
+    /// Must run again whenever `enemies` moved or changed length.
+    pub fn index_enemies(&mut self) {
+        self.enemy_grid
+            .rebuild(self.enemies.iter().map(|e| (e.pos, e.radius)));
+    }
+
+    /// Also rewinds the RNG, so every run with the same seed starts the same.
     pub fn reset(&mut self) {
-        self.player.pos = entities::CENTER;
-        self.player.speed = Vec2::ZERO;
+        self.rng = Rng::new(self.seed);
+        self.player = Entity::new_player();
+        self.power_up_timer = 0;
+        self.glitches.cancel();
+        self.kernel = None;
+        self.kernel_spawned = false;
+        self.glitch_flags = GlitchFlags::default();
+        self.loadout.reset();
+        self.effects = Effects::default();
         self.hp = 3;
         self.mana = 3;
+        self.score = 0;
         self.instability = 0;
-        self.bullet_spawn_timer = 0;
-        self.pather_spawn_timer = 0;
-        self.follower_spawn_timer = 0;
+        self.director.reset();
         self.enemies.clear();
         self.items.clear();
         self.timer = 0.;
     }
 
-    pub fn bsod(&mut self, game_state: &mut GameState, resources: &Resources) {
-        self.glitch_effect.set(20, 2.);
-
-        *game_state = GameState::BSOD;
-        stop_sound(resources.or_did_you);
-        play_sound(resources.bsod_sound, PlaySoundParams::default());
+    pub fn bsod(&mut self, message: &str, events: &mut Vec<Event>) {
+        events.push(Event::Bsod(message.to_owned()));
         self.reset();
+    }
 
-        if !self.disable_tutorial_2_x {
-            self.disable_tutorial_2_x = true;
-            self.show_tutorial_2_1 = true;
+    /// Casting without enough mana wraps it around instead of failing, which
+    /// is the `unlimited_power` bug.
+    pub fn spend_mana(&mut self, cost: u8, events: &mut Vec<Event>) {
+        if let Some(new_mana) = self.mana.checked_sub(cost) {
+            self.mana = new_mana;
+        } else {
+            self.mana = (self.mana + 4 - cost % 4) % 4;
+            events.push(Event::ManaUnderflow);
         }
+    }
 
-        let mut end = true;
+    /// Destroys the enemies in `DESTROY_RANGE`, the `Purge` ability.
+    pub fn power_destroy(&mut self, events: &mut Vec<Event>) {
+        self.power_up_timer = 7;
 
-        for ach in self.achievements.achievements.clone() {
-            if !ach.unlocked {
-                end = false;
+        self.index_enemies();
+        let mut in_range: Vec<usize> = self
+            .enemy_grid
+            .within(self.player.pos, DESTROY_RANGE)
+            .collect();
+        in_range.sort_unstable();
+        for i in in_range {
+            let b = &mut self.enemies[i];
+            // Tanky archetypes take several hits
+            if b.alive && b.hp > 1 {
+                b.hp -= 1;
+                b.hit_anim = 10;
+            } else if b.alive {
+                b.destroy();
+                events.push(Event::EnemyDestroyed { pos: b.pos });
             }
         }
-
-        self.show_credits = end;
+        self.hit_kernel(events);
     }
 
-    pub fn power_destroy(
-        &mut self,
-        resources: &resources::Resources,
-        game_state: &mut GameState,
-        bsod_message: &mut String,
-    ) {
-        self.power_up_timer = 7;
+    /// Damages the Kernel if its weak point is in range of the power.
+    fn hit_kernel(&mut self, events: &mut Vec<Event>) {
+        let Some(kernel) = &mut self.kernel else {
+            return;
+        };
+        if kernel.hit_anim > 0 || (kernel.weak_point() - self.player.pos).length() >= DESTROY_RANGE
+        {
+            return;
+        }
 
-        play_sound(
-            resources.explosion_sound,
-            PlaySoundParams {
-                looped: false,
-                volume: 0.5,
-            },
-        );
+        kernel.hp -= 1;
+        kernel.hit_anim = 20;
+        events.push(Event::KernelHit { hp: kernel.hp });
+        if kernel.hp == 0 {
+            events.push(Event::KernelDefeated { pos: kernel.pos });
+            self.kernel = None;
+        }
+    }
 
-        for b in &mut self.enemies {
-            if (b.pos - self.player.pos).length() < (DESTROY_RANGE) {
-                b.alive = false;
-            }
+    /// The achievements subscriber: turns the gameplay events emitted since
+    /// `first_event` into bugs. Only the first new bug of a tick crashes the
+    /// computer, and known ones raise the instability once per tick.
+    fn apply_bug_rules(&mut self, first_event: usize, events: &mut Vec<Event>) {
+        if self.instability > MAX_UNSTABILITY {
+            self.bug_found("unstable", events);
+            let message = self.achievements.bsod_message("unstable");
+            self.bsod(&message, events);
         }
-        if let Some(new_mana) = self.mana.checked_sub(2) {
-            self.mana = new_mana;
-        } else {
-            if self.mana == 1 {
-                self.mana = 3;
-            } else if self.mana == 0 {
-                self.mana = 2;
-            }
-            if self.achievements.achievements[4].unlocked {
-                self.raise_unstability(resources);
+
+        let bugs: Vec<&str> = events[first_event..].iter().filter_map(bug_for).collect();
+
+        let mut to_raise_unstability = false;
+        let mut display_bsod = None;
+        for id in bugs {
+            if self.bug_found(id, events) {
+                display_bsod = display_bsod.or(Some(id));
             } else {
-                self.achievements.achievements[4].unlock();
-                *bsod_message = self.achievements.achievements[4].name.to_owned();
-                // *game_state = GameState::BSOD;
-                // play_sound(resources.bsod_sound, PlaySoundParams::default());
-                self.bsod(game_state, resources);
+                to_raise_unstability = true;
             }
         }
+
+        if to_raise_unstability {
+            self.raise_unstability(events);
+        }
+
+        if let Some(id) = display_bsod {
+            let message = self.achievements.bsod_message(id);
+            self.bsod(&message, events);
+        }
+    }
+}
+
+/// Which bug a gameplay event reveals. A new bug needs an event, an entry
+/// here and one in `assets/achievements.ron`.
+const fn bug_for(event: &Event) -> Option<&'static str> {
+    match event {
+        Event::HpUnderflow => Some("second_chance"),
+        Event::HpOverflow => Some("over_healed"),
+        Event::ManaOverflow => Some("over_9000"),
+        Event::ManaUnderflow => Some("unlimited_power"),
+        Event::LeftArena => Some("up"),
+        Event::KernelDefeated { .. } => Some("kernel_panic"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{entities::CENTER, events::ItemKind, testing::world};
+
+    fn stats(world: &World, id: &str) -> crate::archetypes::Stats {
+        let archetype = world
+            .archetypes
+            .archetypes
+            .iter()
+            .position(|a| a.id == id)
+            .unwrap();
+        world.archetypes.stats(archetype)
+    }
+
+    /// An enemy of that archetype at `pos`, heading for the player.
+    fn place(world: &mut World, id: &str, pos: Vec2) -> usize {
+        let enemy = Entity::spawn(stats(world, id), pos, world.player.pos, &mut world.rng);
+        world.enemies.push(enemy);
+        world.enemies.len() - 1
+    }
+
+    fn count(events: &[Event], matches: impl Fn(&Event) -> bool) -> usize {
+        events.iter().filter(|e| matches(e)).count()
+    }
+
+    /// Circles around the middle, firing every now and then.
+    fn scripted(tick: u32) -> Input {
+        Input {
+            up: tick % 120 < 30,
+            right: (30..60).contains(&(tick % 120)),
+            down: (60..90).contains(&(tick % 120)),
+            left: tick % 120 >= 90,
+            power: tick % 200 == 199,
+            select: 0,
+        }
+    }
+
+    #[test]
+    fn same_seed_and_inputs_play_out_the_same() {
+        let (mut a, mut b) = (world(9), world(9));
+        let (mut events_a, mut events_b) = (Vec::new(), Vec::new());
+
+        for tick in 0..600 {
+            a.tick(&scripted(tick), &mut events_a);
+            b.tick(&scripted(tick), &mut events_b);
+        }
+
+        assert!(!a.enemies.is_empty());
+        assert_eq!(events_a, events_b);
+        assert_eq!(a.player.pos, b.player.pos);
+        assert_eq!((a.hp, a.mana, a.score), (b.hp, b.mana, b.score));
+        assert_eq!(a.instability, b.instability);
+        let positions = |w: &World| w.enemies.iter().map(|e| e.pos).collect::<Vec<_>>();
+        assert_eq!(positions(&a), positions(&b));
+    }
+
+    #[test]
+    fn crashing_into_the_player_hurts_but_doesnt_score() {
+        let mut world = world(1);
+        place(&mut world, "bullet", CENTER + Vec2::new(0.3, 0.));
+        let mut events = Vec::new();
+
+        world.tick(&Input::default(), &mut events);
+
+        assert_eq!(world.hp, 2);
+        assert_eq!(count(&events, |e| *e == Event::PlayerHit), 1);
+        assert_eq!(
+            count(&events, |e| matches!(e, Event::EnemyCollided { .. })),
+            1
+        );
+        assert_eq!(
+            count(&events, |e| matches!(e, Event::EnemyDestroyed { .. })),
+            0
+        );
+        assert_eq!(world.score, 0);
+        assert!(world.enemies.is_empty());
+    }
+
+    #[test]
+    fn godmode_takes_no_damage() {
+        let mut world = world(1);
+        world.godmode = true;
+        place(&mut world, "bullet", CENTER + Vec2::new(0.3, 0.));
+        let mut events = Vec::new();
+
+        world.tick(&Input::default(), &mut events);
+
+        assert_eq!(world.hp, 3);
+        assert_eq!(count(&events, |e| *e == Event::PlayerHit), 0);
+    }
+
+    #[test]
+    fn purge_spends_mana_and_scores() {
+        let mut world = world(1);
+        place(&mut world, "pather", CENTER + Vec2::new(3., 0.));
+        place(&mut world, "pather", CENTER + Vec2::new(15., 0.));
+        let mut events = Vec::new();
+
+        let power = Input {
+            power: true,
+            ..Input::default()
+        };
+        world.tick(&power, &mut events);
+
+        assert_eq!(world.mana, 1);
+        assert_eq!(
+            count(&events, |e| matches!(e, Event::EnemyDestroyed { .. })),
+            1
+        );
+        assert_eq!(world.score, SCORE_PER_ENEMY);
+        assert_eq!(world.enemies.len(), 1);
+    }
+
+    #[test]
+    fn found_bugs_raise_the_instability_instead_of_crashing() {
+        let mut world = world(1);
+        world.achievements.unlock("over_healed");
+        let mut heal = Entity::new_item(ItemKind::Heal, &mut world.rng);
+        heal.pos = world.player.pos;
+        world.items.push(heal);
+        let mut events = Vec::new();
+
+        world.tick(&Input::default(), &mut events);
+
+        assert_eq!(world.hp, 0);
+        assert_eq!(world.instability, INSTABILITY_UP);
+        assert_eq!(count(&events, |e| *e == Event::HpOverflow), 1);
+        assert_eq!(count(&events, |e| matches!(e, Event::Bsod(_))), 0);
+    }
+
+    #[test]
+    fn new_bugs_crash_and_reset_the_run() {
+        let mut world = world(1);
+        world.mana = 3;
+        let mut mana = Entity::new_item(ItemKind::Mana, &mut world.rng);
+        mana.pos = world.player.pos;
+        world.items.push(mana);
+        let mut events = Vec::new();
+
+        world.tick(&Input::default(), &mut events);
+
+        assert_eq!(count(&events, |e| matches!(e, Event::Bsod(_))), 1);
+        assert!(world.achievements.get("over_9000").unwrap().unlocked);
+        assert_eq!((world.hp, world.mana, world.instability), (3, 3, 0));
+        assert!(world.enemies.is_empty() && world.items.is_empty());
+    }
+
+    #[test]
+    fn reflected_bullets_burst_splitters() {
+        let mut world = world(1);
+        let pos = CENTER + Vec2::new(10., 0.);
+        let splitter = place(&mut world, "splitter", pos);
+        world.enemies[splitter].speed = Vec2::ZERO;
+        let bullet = place(&mut world, "bullet", pos);
+        world.enemies[bullet].friendly = true;
+        world.enemies[bullet].speed = Vec2::ZERO;
+        let mut events = Vec::new();
+
+        world.tick(&Input::default(), &mut events);
+
+        assert_eq!(
+            count(&events, |e| matches!(e, Event::EnemyDestroyed { .. })),
+            1
+        );
+        assert!(!world.enemies.is_empty());
+        assert!(world
+            .enemies
+            .iter()
+            .all(|e| matches!(e.e_type, EntityType::Bullet)));
     }
 }
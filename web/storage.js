@@ -0,0 +1,29 @@
+// localStorage bindings used by src/storage.rs
+miniquad_add_plugin({
+    register_plugin: function (importObject) {
+        function read_string(ptr, len) {
+            return new TextDecoder().decode(new Uint8Array(wasm_memory.buffer, ptr, len));
+        }
+
+        function stored_bytes(key, key_len) {
+            const value = window.localStorage.getItem(read_string(key, key_len));
+            return value === null ? null : new TextEncoder().encode(value);
+        }
+
+        importObject.env.unglitched_storage_len = function (key, key_len) {
+            const bytes = stored_bytes(key, key_len);
+            return bytes === null ? -1 : bytes.length;
+        };
+
+        importObject.env.unglitched_storage_read = function (key, key_len, dst) {
+            const bytes = stored_bytes(key, key_len);
+            new Uint8Array(wasm_memory.buffer, dst, bytes.length).set(bytes);
+        };
+
+        importObject.env.unglitched_storage_write = function (key, key_len, data, len) {
+            window.localStorage.setItem(read_string(key, key_len), read_string(data, len));
+        };
+    },
+    name: "unglitched_storage",
+    version: 1,
+});